@@ -1,220 +1,193 @@
 // Accessibility API for capturing selected text from active applications
 use std::error::Error;
+use std::sync::Mutex;
+
+mod capture;
+// In-memory desktop for exercising the capture algorithm without a display
+#[cfg(test)]
+mod memory;
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "windows")]
+mod win32;
 
+pub use capture::Timing;
+#[cfg(target_os = "linux")]
+use linux::Native;
 #[cfg(target_os = "macos")]
-pub fn get_selected_text() -> Result<String, Box<dyn Error>> {
-    use cocoa::appkit::NSPasteboardTypeString;
-    use cocoa::base::{id, nil};
-    use cocoa::foundation::{NSAutoreleasePool, NSString};
-    use objc::{class, msg_send, sel, sel_impl};
-
-    unsafe {
-        let _pool = NSAutoreleasePool::new(nil);
-        
-        eprintln!("🔍 Starting text capture...");
-        
-        // Save current clipboard
-        let pasteboard: id = msg_send![class!(NSPasteboard), generalPasteboard];
-        let old_contents: id = msg_send![pasteboard, stringForType: NSPasteboardTypeString];
-        let old_string = if old_contents != nil {
-            let c_str = NSString::UTF8String(old_contents);
-            if !c_str.is_null() {
-                let old = std::ffi::CStr::from_ptr(c_str).to_string_lossy().into_owned();
-                eprintln!("💾 Original clipboard saved: {} chars", old.len());
-                Some(old)
-            } else {
-                eprintln!("💾 No original clipboard content");
-                None
-            }
-        } else {
-            eprintln!("💾 Clipboard is empty");
-            None
-        };
-
-        // Simulate Cmd+C to copy selected text
-        eprintln!("⌨️  Simulating Cmd+C...");
-        simulate_copy_command()?;
-        
-        // Longer delay to ensure the copy completes
-        eprintln!("⏳ Waiting for clipboard update...");
-        std::thread::sleep(std::time::Duration::from_millis(200));
-        
-        // Get the newly copied text
-        let pasteboard: id = msg_send![class!(NSPasteboard), generalPasteboard];
-        let new_contents: id = msg_send![pasteboard, stringForType: NSPasteboardTypeString];
-        
-        let selected_text = if new_contents != nil {
-            let c_str = NSString::UTF8String(new_contents);
-            if !c_str.is_null() {
-                let text = std::ffi::CStr::from_ptr(c_str).to_string_lossy().into_owned();
-                eprintln!("📋 Clipboard after Cmd+C: {} chars", text.len());
-                
-                // Check if it's the same as original (meaning nothing was selected/copied)
-                if let Some(ref old) = old_string {
-                    if &text == old {
-                        eprintln!("❌ Clipboard unchanged - no text was selected or Cmd+C failed");
-                        return Err("No text was copied. Please make sure text is selected, or grant accessibility permissions in System Preferences → Security & Privacy → Accessibility.".into());
-                    }
-                }
-                
-                if text.trim().is_empty() {
-                    eprintln!("❌ Clipboard contains only whitespace");
-                    return Err("Selected text is empty. Please select some text first.".into());
-                }
-                
-                // Safe substring that respects UTF-8 character boundaries
-                let preview = text.chars().take(50).collect::<String>();
-                eprintln!("✅ Successfully captured: {}...", preview);
-                text
-            } else {
-                eprintln!("❌ Failed to convert clipboard to UTF8 string");
-                return Err("Failed to read copied text from clipboard.".into());
-            }
-        } else {
-            eprintln!("❌ Clipboard is nil after Cmd+C");
-            return Err("No text was copied. Make sure text is selected or grant accessibility permissions.".into());
-        };
-
-        // Restore original clipboard if it was different
-        if let Some(old) = old_string {
-            if old != selected_text {
-                eprintln!("♻️  Restoring original clipboard...");
-                let ns_string = NSString::alloc(nil);
-                let ns_string = NSString::init_str(ns_string, &old);
-                let _: () = msg_send![pasteboard, clearContents];
-                let _: () = msg_send![pasteboard, setString:ns_string forType:NSPasteboardTypeString];
-                eprintln!("✅ Original clipboard restored");
-            }
-        }
-
-        Ok(selected_text)
-    }
+use macos::Native;
+#[cfg(target_os = "windows")]
+use win32::Native;
+
+// Window (or, on macOS, process) that was active when the selection was
+// captured, so replace_selection can return focus to it after the popup
+static SOURCE_WINDOW: Mutex<Option<isize>> = Mutex::new(None);
+
+/// Why a capture produced no text.
+#[derive(Debug, thiserror::Error)]
+pub enum CaptureError {
+    /// The copy landed, but without any text in it.
+    #[error("No text selected. Please select some text and try again.")]
+    NoSelection,
+    /// The clipboard never changed: nothing was selected, the app ignored the
+    /// shortcut, or it is too slow to answer.
+    #[error("Copying the selection timed out after {0} ms. Make sure text is selected, then try again.")]
+    CopyTimedOut(u128),
 }
 
-#[cfg(target_os = "macos")]
-fn simulate_copy_command() -> Result<(), Box<dyn Error>> {
-    use core_graphics::event::{CGEvent, CGEventFlags, CGEventTapLocation, CGKeyCode};
-    use core_graphics::event_source::{CGEventSource, CGEventSourceStateID};
+/// Text captured from the source app, with the HTML flavor it was copied
+/// with when the app offers one (browsers, Google Docs, Outlook, Notion...).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CapturedText {
+    pub text: String,
+    pub html: Option<String>,
+}
 
-    eprintln!("🎯 Creating event source...");
-    let source = CGEventSource::new(CGEventSourceStateID::CombinedSessionState)
-        .map_err(|_| {
-            eprintln!("❌ Failed to create event source - accessibility permissions may be missing!");
-            "Failed to create event source. Please grant accessibility permissions in System Preferences → Security & Privacy → Accessibility."
-        })?;
-    eprintln!("✅ Event source created");
+/// Every flavor the clipboard held (text, rich text, images, file lists...),
+/// so a capture or paste can put it back untouched.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClipboardSnapshot<F> {
+    /// One entry per pasteboard item; only macOS has several.
+    pub items: Vec<Flavors<F>>,
+}
 
-    // Key code for 'C' is 8
-    let key_code: CGKeyCode = 8;
+/// (format, bytes) pairs making up one clipboard item.
+pub type Flavors<F> = Vec<(F, Vec<u8>)>;
 
-    eprintln!("⌨️  Creating Cmd+C key events...");
-    // Create key down event with Cmd modifier
-    let key_down = CGEvent::new_keyboard_event(source.clone(), key_code, true)
-        .map_err(|_| "Failed to create key down event")?;
-    key_down.set_flags(CGEventFlags::CGEventFlagCommand);
+impl<F> Default for ClipboardSnapshot<F> {
+    fn default() -> Self {
+        Self { items: Vec::new() }
+    }
+}
 
-    // Create key up event with Cmd modifier
-    let key_up = CGEvent::new_keyboard_event(source, key_code, false)
-        .map_err(|_| "Failed to create key up event")?;
-    key_up.set_flags(CGEventFlags::CGEventFlagCommand);
+impl<F> ClipboardSnapshot<F> {
+    pub fn is_empty(&self) -> bool {
+        self.items.iter().all(Vec::is_empty)
+    }
 
-    eprintln!("📤 Posting Cmd+C events...");
-    // Post the events
-    key_down.post(CGEventTapLocation::HID);
-    key_up.post(CGEventTapLocation::HID);
-    eprintln!("✅ Cmd+C events posted");
+    pub fn flavor_count(&self) -> usize {
+        self.items.iter().map(Vec::len).sum()
+    }
+}
 
-    Ok(())
+/// A system clipboard: plain text, every flavor it holds, and a counter
+/// that moves whenever anyone writes to it.
+pub trait ClipboardBackend {
+    /// Pasteboard UTI on macOS, X11 target or MIME type on Linux, format id on Windows.
+    type Format;
+
+    fn read_text(&self) -> Result<Option<String>, Box<dyn Error>>;
+    fn write_text(&self, text: &str) -> Result<(), Box<dyn Error>>;
+    /// HTML flavor as a fragment. On macOS RTF is converted when there is no HTML.
+    fn read_html(&self) -> Result<Option<String>, Box<dyn Error>>;
+    /// Replaces the clipboard with `text` plus `html` as its rich flavor.
+    fn write_rich(&self, text: &str, html: &str) -> Result<(), Box<dyn Error>>;
+    fn snapshot(&self) -> Result<ClipboardSnapshot<Self::Format>, Box<dyn Error>>;
+    /// Replaces the clipboard with exactly the flavors in `snapshot`.
+    fn write(&self, snapshot: &ClipboardSnapshot<Self::Format>) -> Result<(), Box<dyn Error>>;
+    fn change_count(&self) -> Result<u64, Box<dyn Error>>;
 }
 
-#[cfg(target_os = "windows")]
-pub fn get_selected_text() -> Result<String, Box<dyn Error>> {
-    use windows::Win32::UI::Input::KeyboardAndMouse::{
-        SendInput, INPUT, INPUT_KEYBOARD, KEYBDINPUT, KEYEVENTF_KEYUP, VK_CONTROL, VK_C,
-    };
-    use windows::Win32::System::DataExchange::{CloseClipboard, GetClipboardData, OpenClipboard};
-    use windows::Win32::System::Memory::GlobalLock;
-    use std::ptr;
-
-    unsafe {
-        // Simulate Ctrl+C
-        let mut inputs = [
-            INPUT {
-                r#type: INPUT_KEYBOARD,
-                Anonymous: windows::Win32::UI::Input::KeyboardAndMouse::INPUT_0 {
-                    ki: KEYBDINPUT {
-                        wVk: VK_CONTROL,
-                        wScan: 0,
-                        dwFlags: windows::Win32::UI::Input::KeyboardAndMouse::KEYBD_EVENT_FLAGS(0),
-                        time: 0,
-                        dwExtraInfo: 0,
-                    },
-                },
-            },
-            INPUT {
-                r#type: INPUT_KEYBOARD,
-                Anonymous: windows::Win32::UI::Input::KeyboardAndMouse::INPUT_0 {
-                    ki: KEYBDINPUT {
-                        wVk: VK_C,
-                        wScan: 0,
-                        dwFlags: windows::Win32::UI::Input::KeyboardAndMouse::KEYBD_EVENT_FLAGS(0),
-                        time: 0,
-                        dwExtraInfo: 0,
-                    },
-                },
-            },
-            INPUT {
-                r#type: INPUT_KEYBOARD,
-                Anonymous: windows::Win32::UI::Input::KeyboardAndMouse::INPUT_0 {
-                    ki: KEYBDINPUT {
-                        wVk: VK_C,
-                        wScan: 0,
-                        dwFlags: KEYEVENTF_KEYUP,
-                        time: 0,
-                        dwExtraInfo: 0,
-                    },
-                },
-            },
-            INPUT {
-                r#type: INPUT_KEYBOARD,
-                Anonymous: windows::Win32::UI::Input::KeyboardAndMouse::INPUT_0 {
-                    ki: KEYBDINPUT {
-                        wVk: VK_CONTROL,
-                        wScan: 0,
-                        dwFlags: KEYEVENTF_KEYUP,
-                        time: 0,
-                        dwExtraInfo: 0,
-                    },
-                },
-            },
-        ];
-
-        SendInput(&mut inputs, std::mem::size_of::<INPUT>() as i32);
-
-        // Wait for clipboard to update
-        std::thread::sleep(std::time::Duration::from_millis(100));
-
-        // Read from clipboard
-        if OpenClipboard(None).is_ok() {
-            let h_data = GetClipboardData(1); // CF_TEXT = 1
-            if !h_data.is_invalid() {
-                let p_data = GlobalLock(h_data.0);
-                if !p_data.is_null() {
-                    let c_str = std::ffi::CStr::from_ptr(p_data as *const i8);
-                    let text = c_str.to_string_lossy().into_owned();
-                    CloseClipboard().ok();
-                    return Ok(text);
-                }
-            }
-            CloseClipboard().ok();
-        }
-
-        Err("Failed to read clipboard".into())
+/// The app holding the selection, driven through synthesized shortcuts.
+pub trait SelectionSource {
+    /// Reads the selection without touching the clipboard, where the platform
+    /// has a selection buffer of its own (PRIMARY on Linux).
+    fn read_selection(&self) -> Result<Option<CapturedText>, Box<dyn Error>> {
+        Ok(None)
+    }
+
+    fn synthesize_copy(&self) -> Result<(), Box<dyn Error>>;
+    fn synthesize_paste(&self) -> Result<(), Box<dyn Error>>;
+
+    /// Window (or process) that currently has focus, if it can be refocused later.
+    fn focused_window(&self) -> Option<isize> {
+        None
+    }
+
+    fn focus_window(&self, _window: isize) -> Result<(), Box<dyn Error>> {
+        Ok(())
     }
+
+    /// Name of the application owning `window`, for prompts that mention it.
+    fn app_name(&self, _window: isize) -> Option<String> {
+        None
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+pub fn get_selected_text() -> Result<CapturedText, Box<dyn Error>> {
+    eprintln!("🔍 Starting text capture...");
+    capture::capture_selection(&Native, &Native, &Timing::default())
 }
 
-#[cfg(not(any(target_os = "macos", target_os = "windows")))]
-pub fn get_selected_text() -> Result<String, Box<dyn Error>> {
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+pub fn get_selected_text() -> Result<CapturedText, Box<dyn Error>> {
     Err("Platform not supported".into())
 }
 
+/// Pastes `text` over the selection in the window that was active when the
+/// selection was captured, then puts the user's clipboard back. With `html`
+/// the paste carries formatting into apps that accept it.
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+pub fn replace_selection(text: &str, html: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let window = *SOURCE_WINDOW.lock().unwrap();
+    capture::replace(&Native, &Native, window, text, html, &Timing::default())
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+pub fn replace_selection(_text: &str, _html: Option<&str>) -> Result<(), Box<dyn Error>> {
+    Err("Platform not supported".into())
+}
+
+/// Records the currently focused window as the one replace_selection pastes into.
+pub fn remember_source_window() {
+    #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+    let window = Native.focused_window();
+    #[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+    let window = None;
+    *SOURCE_WINDOW.lock().unwrap() = window;
+}
+
+/// Name of the app the last selection was captured from.
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+pub fn source_app_name() -> Option<String> {
+    let window = (*SOURCE_WINDOW.lock().unwrap())?;
+    Native.app_name(window)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+pub fn source_app_name() -> Option<String> {
+    None
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+pub fn read_clipboard_text() -> Result<Option<String>, Box<dyn Error>> {
+    Native.read_text()
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+pub fn read_clipboard_text() -> Result<Option<String>, Box<dyn Error>> {
+    Err("Platform not supported".into())
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+pub fn write_clipboard_text(text: &str) -> Result<(), Box<dyn Error>> {
+    Native.write_text(text)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+pub fn write_clipboard_text(_text: &str) -> Result<(), Box<dyn Error>> {
+    Err("Platform not supported".into())
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+pub fn write_clipboard_rich(text: &str, html: &str) -> Result<(), Box<dyn Error>> {
+    Native.write_rich(text, html)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+pub fn write_clipboard_rich(_text: &str, _html: &str) -> Result<(), Box<dyn Error>> {
+    Err("Platform not supported".into())
+}
@@ -0,0 +1,325 @@
+// Save → copy → diff → restore, independent of the platform behind the traits
+use super::{CaptureError, CapturedText, ClipboardBackend, ClipboardSnapshot, SelectionSource};
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+/// How long each step of a capture or paste may take.
+#[derive(Debug, Clone)]
+pub struct Timing {
+    /// How long a simulated copy may take to reach the clipboard. Electron
+    /// apps and remote desktop sessions can take well over a second.
+    pub copy_timeout: Duration,
+    pub poll_interval: Duration,
+    /// Time for the window manager to move focus back to the source window.
+    pub focus_settle: Duration,
+    /// The target app reads the clipboard asynchronously after the key events.
+    pub paste_settle: Duration,
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Self {
+            copy_timeout: Duration::from_millis(2000),
+            poll_interval: Duration::from_millis(15),
+            focus_settle: Duration::from_millis(100),
+            paste_settle: Duration::from_millis(300),
+        }
+    }
+}
+
+/// Reads the selection from the source's own buffer when it has one,
+/// otherwise copies it to the clipboard and restores the user's clipboard.
+pub fn capture_selection<C, S>(
+    clipboard: &C,
+    source: &S,
+    timing: &Timing,
+) -> Result<CapturedText, Box<dyn Error>>
+where
+    C: ClipboardBackend,
+    S: SelectionSource,
+{
+    match source.read_selection() {
+        Ok(Some(captured)) if !captured.text.trim().is_empty() => {
+            let preview = captured.text.chars().take(50).collect::<String>();
+            eprintln!("✅ Captured PRIMARY selection: {}...", preview);
+            return Ok(captured);
+        }
+        Ok(Some(_)) => eprintln!("💾 PRIMARY selection is empty"),
+        Ok(None) => {}
+        // Some apps never set PRIMARY, so copy the selection to CLIPBOARD instead
+        Err(e) => eprintln!("⚠️  PRIMARY selection unavailable: {}", e),
+    }
+
+    capture_via_copy(clipboard, source, timing)
+}
+
+// Sends the copy shortcut and waits for the clipboard to change, then restores
+// the user's clipboard. Comparing change counts rather than text means
+// re-selecting what is already on the clipboard still counts as a copy.
+fn capture_via_copy<C, S>(clipboard: &C, source: &S, timing: &Timing) -> Result<CapturedText, Box<dyn Error>>
+where
+    C: ClipboardBackend,
+    S: SelectionSource,
+{
+    let snapshot = save_clipboard(clipboard);
+    let old_count = clipboard.change_count()?;
+
+    source.synthesize_copy()?;
+
+    eprintln!("⏳ Waiting for clipboard update...");
+    let started = Instant::now();
+    let deadline = started + timing.copy_timeout;
+    while clipboard.change_count()? == old_count {
+        if Instant::now() >= deadline {
+            let timeout = timing.copy_timeout.as_millis();
+            eprintln!("❌ Clipboard unchanged after {} ms", timeout);
+            return Err(CaptureError::CopyTimedOut(timeout).into());
+        }
+        std::thread::sleep(timing.poll_interval);
+    }
+    eprintln!("📋 Clipboard changed after {} ms", started.elapsed().as_millis());
+
+    // Some apps clear the clipboard first and write the data a moment later
+    let mut copied = clipboard.read_text()?;
+    while copied.is_none() && Instant::now() < deadline {
+        std::thread::sleep(timing.poll_interval);
+        copied = clipboard.read_text()?;
+    }
+    // Read before restoring, while the copy is still on the clipboard
+    let html = match copied {
+        Some(_) => clipboard.read_html().unwrap_or_else(|e| {
+            eprintln!("⚠️  Failed to read the HTML flavor: {}", e);
+            None
+        }),
+        None => None,
+    };
+    let copied_count = clipboard.change_count()?;
+    restore_clipboard(clipboard, &snapshot, copied_count)?;
+
+    match copied {
+        Some(text) if !text.trim().is_empty() => {
+            // Safe substring that respects UTF-8 character boundaries
+            let preview = text.chars().take(50).collect::<String>();
+            eprintln!("✅ Successfully captured: {}... (html: {})", preview, html.is_some());
+            Ok(CapturedText { text, html })
+        }
+        _ => {
+            eprintln!("❌ Copy contained no text");
+            Err(CaptureError::NoSelection.into())
+        }
+    }
+}
+
+/// Focuses `window`, pastes `text` into it through the clipboard, then puts
+/// the user's clipboard back. `html` rides along as the rich flavor.
+pub fn replace<C, S>(
+    clipboard: &C,
+    source: &S,
+    window: Option<isize>,
+    text: &str,
+    html: Option<&str>,
+    timing: &Timing,
+) -> Result<(), Box<dyn Error>>
+where
+    C: ClipboardBackend,
+    S: SelectionSource,
+{
+    eprintln!("✍️  Replacing selection: {} chars", text.len());
+
+    if let Some(window) = window {
+        source.focus_window(window)?;
+        std::thread::sleep(timing.focus_settle);
+    }
+
+    let original = save_clipboard(clipboard);
+
+    match html {
+        Some(html) => clipboard.write_rich(text, html)?,
+        None => clipboard.write_text(text)?,
+    }
+    let pasted_count = clipboard.change_count()?;
+    eprintln!("⌨️  Simulating paste...");
+    source.synthesize_paste()?;
+
+    std::thread::sleep(timing.paste_settle);
+
+    restore_clipboard(clipboard, &original, pasted_count)
+}
+
+// Saves the clipboard, logging instead of failing: losing the user's clipboard
+// is better than refusing to capture
+fn save_clipboard<C: ClipboardBackend>(clipboard: &C) -> ClipboardSnapshot<C::Format> {
+    match clipboard.snapshot() {
+        Ok(snapshot) => {
+            eprintln!("💾 Original clipboard saved: {} flavor(s)", snapshot.flavor_count());
+            snapshot
+        }
+        Err(e) => {
+            eprintln!("⚠️  Failed to save clipboard: {}", e);
+            ClipboardSnapshot::default()
+        }
+    }
+}
+
+// Puts `snapshot` back unless another app wrote to the clipboard after `expected_count`
+fn restore_clipboard<C: ClipboardBackend>(
+    clipboard: &C,
+    snapshot: &ClipboardSnapshot<C::Format>,
+    expected_count: u64,
+) -> Result<(), Box<dyn Error>> {
+    if snapshot.is_empty() {
+        return Ok(());
+    }
+    if clipboard.change_count()? != expected_count {
+        eprintln!("⏭️  Clipboard changed by another app, leaving it alone");
+        return Ok(());
+    }
+
+    eprintln!("♻️  Restoring original clipboard...");
+    clipboard.write(snapshot)?;
+    eprintln!("✅ Original clipboard restored");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accessibility::memory::{rich_snapshot, text_snapshot, MemoryDesktop};
+
+    fn timing() -> Timing {
+        Timing {
+            copy_timeout: Duration::from_millis(50),
+            poll_interval: Duration::from_millis(1),
+            focus_settle: Duration::ZERO,
+            paste_settle: Duration::ZERO,
+        }
+    }
+
+    fn capture_error(result: Result<CapturedText, Box<dyn Error>>) -> CaptureError {
+        let error = result.expect_err("capture should fail");
+        match error.downcast::<CaptureError>() {
+            Ok(error) => *error,
+            Err(other) => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[test]
+    fn captures_selection_and_restores_clipboard() {
+        let desktop = MemoryDesktop::new();
+        desktop.set_clipboard(text_snapshot("what the user copied"));
+        desktop.select_rich("hello world", "<b>hello</b> world");
+
+        let captured = capture_selection(&desktop, &desktop, &timing()).unwrap();
+
+        assert_eq!(captured.text, "hello world");
+        assert_eq!(captured.html.as_deref(), Some("<b>hello</b> world"));
+        assert_eq!(desktop.clipboard(), text_snapshot("what the user copied"));
+    }
+
+    #[test]
+    fn times_out_when_the_app_ignores_copy() {
+        let desktop = MemoryDesktop::new();
+        desktop.set_clipboard(text_snapshot("original"));
+        desktop.select("hello");
+        desktop.ignore_copy(true);
+
+        let error = capture_error(capture_selection(&desktop, &desktop, &timing()));
+
+        assert!(matches!(error, CaptureError::CopyTimedOut(50)));
+        assert_eq!(desktop.clipboard(), text_snapshot("original"));
+    }
+
+    #[test]
+    fn times_out_when_nothing_is_selected() {
+        let desktop = MemoryDesktop::new();
+        desktop.set_clipboard(text_snapshot("original"));
+
+        let error = capture_error(capture_selection(&desktop, &desktop, &timing()));
+
+        assert!(matches!(error, CaptureError::CopyTimedOut(_)));
+        assert_eq!(desktop.clipboard(), text_snapshot("original"));
+    }
+
+    #[test]
+    fn rejects_a_blank_selection_and_restores_clipboard() {
+        let desktop = MemoryDesktop::new();
+        desktop.set_clipboard(text_snapshot("original"));
+        desktop.select("  \n ");
+
+        let error = capture_error(capture_selection(&desktop, &desktop, &timing()));
+
+        assert!(matches!(error, CaptureError::NoSelection));
+        assert_eq!(desktop.clipboard(), text_snapshot("original"));
+    }
+
+    #[test]
+    fn reselecting_the_clipboard_text_still_counts_as_a_copy() {
+        let desktop = MemoryDesktop::new();
+        desktop.set_clipboard(text_snapshot("same text"));
+        desktop.select("same text");
+
+        let captured = capture_selection(&desktop, &desktop, &timing()).unwrap();
+
+        assert_eq!(captured.text, "same text");
+        assert_eq!(desktop.clipboard(), text_snapshot("same text"));
+    }
+
+    #[test]
+    fn primary_selection_skips_the_clipboard() {
+        let desktop = MemoryDesktop::new();
+        desktop.set_clipboard(text_snapshot("original"));
+        desktop.set_primary("from primary");
+        desktop.select("from copy");
+        let count = desktop.change_count().unwrap();
+
+        let captured = capture_selection(&desktop, &desktop, &timing()).unwrap();
+
+        assert_eq!(captured.text, "from primary");
+        assert_eq!(desktop.change_count().unwrap(), count);
+    }
+
+    #[test]
+    fn blank_primary_selection_falls_back_to_copy() {
+        let desktop = MemoryDesktop::new();
+        desktop.set_primary(" ");
+        desktop.select("from copy");
+
+        let captured = capture_selection(&desktop, &desktop, &timing()).unwrap();
+
+        assert_eq!(captured.text, "from copy");
+    }
+
+    #[test]
+    fn replace_pastes_into_the_window_and_restores_clipboard() {
+        let desktop = MemoryDesktop::new();
+        let original = rich_snapshot("original", "<i>original</i>");
+        desktop.set_clipboard(original.clone());
+
+        replace(&desktop, &desktop, Some(7), "rephrased", Some("<p>rephrased</p>"), &timing()).unwrap();
+
+        assert_eq!(desktop.focused(), Some(7));
+        assert_eq!(desktop.pasted(), vec!["rephrased".to_string()]);
+        assert_eq!(desktop.clipboard(), original);
+    }
+
+    #[test]
+    fn replace_leaves_a_newer_clipboard_alone() {
+        let desktop = MemoryDesktop::new();
+        desktop.set_clipboard(text_snapshot("original"));
+        desktop.copy_after_paste(text_snapshot("copied elsewhere"));
+
+        replace(&desktop, &desktop, None, "rephrased", None, &timing()).unwrap();
+
+        assert_eq!(desktop.pasted(), vec!["rephrased".to_string()]);
+        assert_eq!(desktop.clipboard(), text_snapshot("copied elsewhere"));
+    }
+
+    #[test]
+    fn nothing_to_restore_into_an_empty_clipboard() {
+        let desktop = MemoryDesktop::new();
+
+        replace(&desktop, &desktop, None, "rephrased", None, &timing()).unwrap();
+
+        assert_eq!(desktop.clipboard(), text_snapshot("rephrased"));
+    }
+}
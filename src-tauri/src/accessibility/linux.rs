@@ -0,0 +1,252 @@
+// X11 and Wayland selections, with Ctrl+C/Ctrl+V through XTest or uinput
+use super::{CapturedText, ClipboardBackend, SelectionSource};
+use std::error::Error;
+
+mod uinput;
+mod wayland;
+mod x11;
+
+// X11 selections and Wayland offers hold a single item of (target or MIME type, bytes)
+type ClipboardSnapshot = super::ClipboardSnapshot<String>;
+type Flavors = super::Flavors<String>;
+
+// Targets the plain text of a rich write is offered under, next to text/html
+const TEXT_TARGETS: [&str; 4] = ["text/plain;charset=utf-8", "text/plain", "UTF8_STRING", "TEXT"];
+
+/// CLIPBOARD and PRIMARY on whichever display server the session runs.
+pub struct Native;
+
+impl ClipboardBackend for Native {
+    type Format = String;
+
+    fn read_text(&self) -> Result<Option<String>, Box<dyn Error>> {
+        read_clipboard_text()
+    }
+
+    fn write_text(&self, text: &str) -> Result<(), Box<dyn Error>> {
+        write_clipboard_text(text)
+    }
+
+    fn read_html(&self) -> Result<Option<String>, Box<dyn Error>> {
+        read_html(x11::Selection::Clipboard)
+    }
+
+    fn write_rich(&self, text: &str, html: &str) -> Result<(), Box<dyn Error>> {
+        let mut flavors: Flavors = TEXT_TARGETS
+            .iter()
+            .map(|target| (target.to_string(), text.as_bytes().to_vec()))
+            .collect();
+        // Without a charset, browsers read the fragment as Latin-1
+        let html = format!("<meta charset=\"utf-8\">{}", html);
+        flavors.push(("text/html".to_string(), html.into_bytes()));
+        write_clipboard(&ClipboardSnapshot { items: vec![flavors] })
+    }
+
+    fn snapshot(&self) -> Result<ClipboardSnapshot, Box<dyn Error>> {
+        snapshot_clipboard()
+    }
+
+    fn write(&self, snapshot: &ClipboardSnapshot) -> Result<(), Box<dyn Error>> {
+        write_clipboard(snapshot)
+    }
+
+    fn change_count(&self) -> Result<u64, Box<dyn Error>> {
+        clipboard_change_count()
+    }
+}
+
+impl SelectionSource for Native {
+    // The PRIMARY selection already holds the highlighted text, no key events needed
+    fn read_selection(&self) -> Result<Option<CapturedText>, Box<dyn Error>> {
+        let Some(text) = read_primary_selection()? else {
+            return Ok(None);
+        };
+        let html = read_html(x11::Selection::Primary).unwrap_or_else(|e| {
+            eprintln!("⚠️  Failed to read the HTML flavor: {}", e);
+            None
+        });
+        Ok(Some(CapturedText { text, html }))
+    }
+
+    fn synthesize_copy(&self) -> Result<(), Box<dyn Error>> {
+        eprintln!("⌨️  Simulating Ctrl+C...");
+        send_linux_shortcut(uinput::simulate_copy, x11::simulate_copy)
+    }
+
+    fn synthesize_paste(&self) -> Result<(), Box<dyn Error>> {
+        simulate_paste()
+    }
+
+    fn focused_window(&self) -> Option<isize> {
+        active_window()
+    }
+
+    fn focus_window(&self, window: isize) -> Result<(), Box<dyn Error>> {
+        activate_window(window)
+    }
+
+    fn app_name(&self, window: isize) -> Option<String> {
+        x11::window_class(window as u32).unwrap_or_else(|e| {
+            eprintln!("⚠️  Failed to read the window class: {}", e);
+            None
+        })
+    }
+}
+
+fn read_primary_selection() -> Result<Option<String>, Box<dyn Error>> {
+    if wayland::is_available() {
+        match wayland::read_primary() {
+            Ok(text) => return Ok(text),
+            // Compositors without data-control (GNOME): try XWayland next
+            Err(e) => eprintln!("⚠️  Wayland data-control unavailable: {}", e),
+        }
+    }
+    if x11::is_available() {
+        return x11::read_selection(x11::Selection::Primary);
+    }
+    Err("No X11 or Wayland display found".into())
+}
+
+fn read_clipboard_text() -> Result<Option<String>, Box<dyn Error>> {
+    if wayland::is_available() {
+        match wayland::read_clipboard() {
+            Ok(text) => return Ok(text),
+            Err(e) => eprintln!("⚠️  Wayland data-control unavailable: {}", e),
+        }
+    }
+    if x11::is_available() {
+        return x11::read_selection(x11::Selection::Clipboard);
+    }
+    Err("No X11 or Wayland display found".into())
+}
+
+fn read_html(selection: x11::Selection) -> Result<Option<String>, Box<dyn Error>> {
+    if wayland::is_available() {
+        let html = match selection {
+            x11::Selection::Primary => wayland::read_primary_html(),
+            x11::Selection::Clipboard => wayland::read_clipboard_html(),
+        };
+        match html {
+            Ok(bytes) => return Ok(bytes.map(|bytes| decode_html(&bytes))),
+            Err(e) => eprintln!("⚠️  Wayland data-control unavailable: {}", e),
+        }
+    }
+    if x11::is_available() {
+        return Ok(x11::read_html(selection)?.map(|bytes| decode_html(&bytes)));
+    }
+    Err("No X11 or Wayland display found".into())
+}
+
+// Firefox offers text/html as UTF-16 behind a byte order mark, everyone else as UTF-8
+fn decode_html(bytes: &[u8]) -> String {
+    match bytes {
+        [0xFF, 0xFE, rest @ ..] => {
+            let units: Vec<u16> = rest
+                .chunks_exact(2)
+                .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+fn write_clipboard_text(text: &str) -> Result<(), Box<dyn Error>> {
+    if wayland::is_available() {
+        match wayland::write_clipboard(text) {
+            Ok(()) => return Ok(()),
+            Err(e) => eprintln!("⚠️  Wayland data-control unavailable: {}", e),
+        }
+    }
+    if x11::is_available() {
+        return x11::write_clipboard(text);
+    }
+    Err("No X11 or Wayland display found".into())
+}
+
+fn snapshot_clipboard() -> Result<ClipboardSnapshot, Box<dyn Error>> {
+    if wayland::is_available() {
+        match wayland::snapshot_clipboard() {
+            Ok(flavors) => return Ok(ClipboardSnapshot { items: vec![flavors] }),
+            Err(e) => eprintln!("⚠️  Wayland data-control unavailable: {}", e),
+        }
+    }
+    if x11::is_available() {
+        let flavors = x11::snapshot_clipboard()?;
+        return Ok(ClipboardSnapshot { items: vec![flavors] });
+    }
+    Err("No X11 or Wayland display found".into())
+}
+
+fn write_clipboard(snapshot: &ClipboardSnapshot) -> Result<(), Box<dyn Error>> {
+    let flavors = snapshot.items.concat();
+    if wayland::is_available() {
+        match wayland::write_clipboard_contents(&flavors) {
+            Ok(()) => return Ok(()),
+            Err(e) => eprintln!("⚠️  Wayland data-control unavailable: {}", e),
+        }
+    }
+    if x11::is_available() {
+        return x11::write_clipboard_contents(&flavors);
+    }
+    Err("No X11 or Wayland display found".into())
+}
+
+fn clipboard_change_count() -> Result<u64, Box<dyn Error>> {
+    if wayland::is_available() {
+        match wayland::selection_serial() {
+            Ok(serial) => return Ok(serial),
+            Err(e) => eprintln!("⚠️  Wayland data-control unavailable: {}", e),
+        }
+    }
+    if x11::is_available() {
+        return x11::clipboard_generation();
+    }
+    Err("No X11 or Wayland display found".into())
+}
+
+// Wayland gives clients no way to focus other windows; hiding the popup hands
+// focus back on its own there
+fn active_window() -> Option<isize> {
+    if wayland::is_available() || !x11::is_available() {
+        return None;
+    }
+    match x11::active_window() {
+        Ok(window) => window.map(|window| window as isize),
+        Err(e) => {
+            eprintln!("⚠️  Failed to read the active window: {}", e);
+            None
+        }
+    }
+}
+
+fn activate_window(window: isize) -> Result<(), Box<dyn Error>> {
+    x11::activate_window(window as u32)
+}
+
+fn simulate_paste() -> Result<(), Box<dyn Error>> {
+    send_linux_shortcut(uinput::simulate_paste, x11::simulate_paste)
+}
+
+// XTest events only reach X11 windows, so native Wayland sessions go through uinput
+fn send_linux_shortcut(
+    via_uinput: fn() -> Result<(), Box<dyn Error>>,
+    via_xtest: fn() -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    if wayland::is_available() {
+        match via_uinput() {
+            Ok(()) => return Ok(()),
+            Err(e) if x11::is_available() => eprintln!("⚠️  {}", e),
+            Err(e) => return Err(e),
+        }
+        return via_xtest();
+    }
+
+    match via_xtest() {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            eprintln!("⚠️  XTest failed, trying uinput: {}", e);
+            via_uinput()
+        }
+    }
+}
@@ -0,0 +1,60 @@
+// Virtual keyboard on /dev/uinput, for Wayland sessions where XTest only reaches XWayland windows
+use evdev::uinput::VirtualDevice;
+use evdev::{AttributeSet, KeyCode, KeyEvent};
+use std::error::Error;
+use std::sync::Mutex;
+use std::time::Duration;
+
+// Every key the device may ever send has to be declared up front
+const KEYS: [KeyCode; 3] = [KeyCode::KEY_LEFTCTRL, KeyCode::KEY_C, KeyCode::KEY_V];
+
+// Created once and kept, since the compositor needs time to adopt a new device
+static DEVICE: Mutex<Option<VirtualDevice>> = Mutex::new(None);
+
+/// Sends Ctrl+C as key codes, so it follows the physical QWERTY position.
+pub fn simulate_copy() -> Result<(), Box<dyn Error>> {
+    press_with_control(KeyCode::KEY_C)
+}
+
+pub fn simulate_paste() -> Result<(), Box<dyn Error>> {
+    press_with_control(KeyCode::KEY_V)
+}
+
+fn press_with_control(key: KeyCode) -> Result<(), Box<dyn Error>> {
+    let mut guard = DEVICE.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(create_device()?);
+        std::thread::sleep(Duration::from_millis(300));
+    }
+    let device = guard.as_mut().unwrap();
+
+    for (code, value) in [
+        (KeyCode::KEY_LEFTCTRL, 1),
+        (key, 1),
+        (key, 0),
+        (KeyCode::KEY_LEFTCTRL, 0),
+    ] {
+        device.emit(&[*KeyEvent::new(code, value)])?;
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    Ok(())
+}
+
+fn create_device() -> Result<VirtualDevice, Box<dyn Error>> {
+    let keys: AttributeSet<KeyCode> = KEYS.into_iter().collect();
+
+    let device = VirtualDevice::builder()
+        .map_err(|e| {
+            if e.kind() == std::io::ErrorKind::PermissionDenied {
+                "Cannot open /dev/uinput to simulate key presses. Add your user to the input group \
+                 or install a udev rule granting access to /dev/uinput."
+                    .to_string()
+            } else {
+                format!("Failed to open /dev/uinput: {}", e)
+            }
+        })?
+        .name("Rephraser virtual keyboard")
+        .with_keys(&keys)?
+        .build()?;
+    Ok(device)
+}
@@ -0,0 +1,289 @@
+// Wayland clipboards through the wlr/ext data-control protocol
+use std::error::Error;
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, OnceLock};
+use std::time::{Duration, Instant};
+use super::Flavors;
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::wl_registry::WlRegistry;
+use wayland_client::protocol::wl_seat::WlSeat;
+use wayland_client::{event_created_child, Connection, Dispatch, QueueHandle};
+use wayland_protocols_wlr::data_control::v1::client::zwlr_data_control_device_v1::{
+    self, ZwlrDataControlDeviceV1,
+};
+use wayland_protocols_wlr::data_control::v1::client::zwlr_data_control_manager_v1::ZwlrDataControlManagerV1;
+use wayland_protocols_wlr::data_control::v1::client::zwlr_data_control_offer_v1::ZwlrDataControlOfferV1;
+use wl_clipboard_rs::copy::{self, MimeSource, Options, Source};
+use wl_clipboard_rs::paste::{
+    get_contents, get_mime_types_ordered, ClipboardType, Error as PasteError, MimeType, Seat,
+};
+
+// The source app writes into a pipe; a hung app must not hang the capture
+const READ_TIMEOUT: Duration = Duration::from_millis(500);
+
+// How long a write waits for the compositor to announce it, so the change is
+// already counted when the caller reads the serial afterwards
+const WRITE_SETTLE: Duration = Duration::from_millis(200);
+
+static SELECTION_WATCH: OnceLock<Result<Arc<SelectionWatch>, String>> = OnceLock::new();
+
+pub fn is_available() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").is_some_and(|display| !display.is_empty())
+}
+
+/// Reads the primary selection (the highlighted text). Fails when the
+/// compositor lacks data-control version 2 or ext-data-control.
+pub fn read_primary() -> Result<Option<String>, Box<dyn Error>> {
+    read_text(ClipboardType::Primary)
+}
+
+pub fn read_clipboard() -> Result<Option<String>, Box<dyn Error>> {
+    read_text(ClipboardType::Regular)
+}
+
+pub fn read_primary_html() -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+    read(ClipboardType::Primary, MimeType::Specific("text/html"))
+}
+
+pub fn read_clipboard_html() -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+    read(ClipboardType::Regular, MimeType::Specific("text/html"))
+}
+
+/// Offers `text` on the clipboard from a background thread until another
+/// client copies something.
+pub fn write_clipboard(text: &str) -> Result<(), Box<dyn Error>> {
+    let before = selection_serial().ok();
+    Options::new().copy(
+        Source::Bytes(text.as_bytes().into()),
+        copy::MimeType::Text,
+    )?;
+    settle(before);
+    Ok(())
+}
+
+/// Every MIME type on the clipboard with its data, in the owner's order.
+pub fn snapshot_clipboard() -> Result<Flavors, Box<dyn Error>> {
+    let mime_types = match get_mime_types_ordered(ClipboardType::Regular, Seat::Unspecified) {
+        Ok(mime_types) => mime_types,
+        Err(PasteError::NoSeats | PasteError::ClipboardEmpty) => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut flavors = Vec::new();
+    for mime_type in mime_types {
+        match read(ClipboardType::Regular, MimeType::Specific(&mime_type)) {
+            Ok(Some(bytes)) => flavors.push((mime_type, bytes)),
+            Ok(None) => {}
+            Err(e) => eprintln!("⚠️  Skipping clipboard type {}: {}", mime_type, e),
+        }
+    }
+    Ok(flavors)
+}
+
+/// Offers exactly `flavors` on the clipboard, as they were snapshotted.
+pub fn write_clipboard_contents(flavors: &[(String, Vec<u8>)]) -> Result<(), Box<dyn Error>> {
+    let sources = flavors
+        .iter()
+        .map(|(mime_type, bytes)| MimeSource {
+            source: Source::Bytes(bytes.as_slice().into()),
+            mime_type: copy::MimeType::Specific(mime_type.clone()),
+        })
+        .collect();
+
+    let before = selection_serial().ok();
+    let mut options = Options::new();
+    options.omit_additional_text_mime_types(true);
+    options.copy_multi(sources)?;
+    settle(before);
+    Ok(())
+}
+
+/// Counts CLIPBOARD selection changes, including a copy of the same text
+/// again. A data-control device opened on first use reports every new
+/// selection offer from a background thread; this reads its count.
+pub fn selection_serial() -> Result<u64, Box<dyn Error>> {
+    let watch = SELECTION_WATCH.get_or_init(|| watch_selection().map_err(|e| e.to_string()));
+    match watch {
+        Ok(watch) if watch.alive.load(Ordering::Acquire) => Ok(watch.serial.load(Ordering::Acquire)),
+        Ok(_) => Err("The Wayland selection watcher stopped".into()),
+        Err(e) => Err(e.clone().into()),
+    }
+}
+
+// Waits until our own write shows up as a new selection
+fn settle(before: Option<u64>) {
+    let Some(before) = before else {
+        return;
+    };
+    let deadline = Instant::now() + WRITE_SETTLE;
+    while Instant::now() < deadline {
+        match selection_serial() {
+            Ok(serial) if serial == before => std::thread::sleep(Duration::from_millis(5)),
+            _ => return,
+        }
+    }
+}
+
+struct SelectionWatch {
+    serial: AtomicU64,
+    alive: AtomicBool,
+}
+
+// Dispatch state of the watcher thread. Offers are only tracked to destroy
+// them once replaced; their data is never read through this connection.
+struct Watcher {
+    watch: Arc<SelectionWatch>,
+    selection: Option<ZwlrDataControlOfferV1>,
+    primary: Option<ZwlrDataControlOfferV1>,
+}
+
+fn watch_selection() -> Result<Arc<SelectionWatch>, Box<dyn Error>> {
+    let conn = Connection::connect_to_env()?;
+    let (globals, mut queue) = registry_queue_init::<Watcher>(&conn)?;
+    let qh = queue.handle();
+    let seat: WlSeat = globals.bind(&qh, 1..=1, ())?;
+    let manager: ZwlrDataControlManagerV1 = globals.bind(&qh, 1..=2, ())?;
+    let device = manager.get_data_device(&seat, &qh, ());
+
+    let watch = Arc::new(SelectionWatch {
+        serial: AtomicU64::new(0),
+        alive: AtomicBool::new(true),
+    });
+    let mut watcher = Watcher {
+        watch: watch.clone(),
+        selection: None,
+        primary: None,
+    };
+    // The compositor announces the current selection right away
+    queue.roundtrip(&mut watcher)?;
+
+    std::thread::spawn(move || {
+        let _keep_alive = (conn, seat, manager, device);
+        while watcher.watch.alive.load(Ordering::Acquire) {
+            if let Err(e) = queue.blocking_dispatch(&mut watcher) {
+                eprintln!("⚠️  Wayland selection watcher stopped: {}", e);
+                break;
+            }
+        }
+        watcher.watch.alive.store(false, Ordering::Release);
+    });
+    Ok(watch)
+}
+
+impl Dispatch<ZwlrDataControlDeviceV1, ()> for Watcher {
+    fn event(
+        state: &mut Self,
+        _device: &ZwlrDataControlDeviceV1,
+        event: zwlr_data_control_device_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_data_control_device_v1::Event::Selection { id } => {
+                state.watch.serial.fetch_add(1, Ordering::AcqRel);
+                let old = std::mem::replace(&mut state.selection, id);
+                state.retire(old);
+            }
+            zwlr_data_control_device_v1::Event::PrimarySelection { id } => {
+                let old = std::mem::replace(&mut state.primary, id);
+                state.retire(old);
+            }
+            zwlr_data_control_device_v1::Event::Finished => {
+                state.watch.alive.store(false, Ordering::Release);
+            }
+            _ => {}
+        }
+    }
+
+    event_created_child!(Watcher, ZwlrDataControlDeviceV1, [
+        zwlr_data_control_device_v1::EVT_DATA_OFFER_OPCODE => (ZwlrDataControlOfferV1, ()),
+    ]);
+}
+
+impl Watcher {
+    // Destroys a replaced offer unless it is still the other selection
+    fn retire(&self, offer: Option<ZwlrDataControlOfferV1>) {
+        let Some(offer) = offer else {
+            return;
+        };
+        if self.selection.as_ref() != Some(&offer) && self.primary.as_ref() != Some(&offer) {
+            offer.destroy();
+        }
+    }
+}
+
+// The remaining objects send nothing the watcher needs
+impl Dispatch<WlRegistry, GlobalListContents> for Watcher {
+    fn event(
+        _state: &mut Self,
+        _registry: &WlRegistry,
+        _event: <WlRegistry as wayland_client::Proxy>::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<WlSeat, ()> for Watcher {
+    fn event(
+        _state: &mut Self,
+        _seat: &WlSeat,
+        _event: <WlSeat as wayland_client::Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrDataControlManagerV1, ()> for Watcher {
+    fn event(
+        _state: &mut Self,
+        _manager: &ZwlrDataControlManagerV1,
+        _event: <ZwlrDataControlManagerV1 as wayland_client::Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrDataControlOfferV1, ()> for Watcher {
+    fn event(
+        _state: &mut Self,
+        _offer: &ZwlrDataControlOfferV1,
+        _event: <ZwlrDataControlOfferV1 as wayland_client::Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+fn read_text(clipboard: ClipboardType) -> Result<Option<String>, Box<dyn Error>> {
+    Ok(read(clipboard, MimeType::Text)?.map(|bytes| String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+fn read(clipboard: ClipboardType, mime_type: MimeType<'_>) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+    let mut pipe = match get_contents(clipboard, Seat::Unspecified, mime_type) {
+        Ok((pipe, _mime_type)) => pipe,
+        Err(PasteError::NoSeats | PasteError::ClipboardEmpty | PasteError::NoMimeType) => {
+            return Ok(None)
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut bytes = Vec::new();
+        let _ = tx.send(pipe.read_to_end(&mut bytes).map(|_| bytes));
+    });
+
+    let bytes = rx
+        .recv_timeout(READ_TIMEOUT)
+        .map_err(|_| "Timed out reading the Wayland selection")??;
+    Ok(Some(bytes))
+}
@@ -0,0 +1,474 @@
+// X11 selections and XTest key events, over a short-lived connection per call
+// except for clipboard polling
+use std::error::Error;
+use super::Flavors;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ClientMessageEvent, ConnectionExt as _, CreateWindowAux, EventMask, PropMode,
+    Property, SelectionNotifyEvent, SelectionRequestEvent, Window, WindowClass, KEY_PRESS_EVENT,
+    KEY_RELEASE_EVENT, SELECTION_NOTIFY_EVENT,
+};
+use x11rb::protocol::xtest::{self, ConnectionExt as _};
+use x11rb::protocol::Event;
+use x11rb::wrapper::ConnectionExt as _;
+use x11rb::rust_connection::RustConnection;
+use x11rb::{COPY_DEPTH_FROM_PARENT, COPY_FROM_PARENT, CURRENT_TIME, NONE};
+
+// How long a selection owner gets to answer before we give up on it
+const SELECTION_TIMEOUT: Duration = Duration::from_millis(500);
+
+// Capture polls the clipboard generation every few milliseconds, so that one
+// connection is kept open instead of reconnecting on each poll
+static GENERATION_SESSION: Mutex<Option<Session>> = Mutex::new(None);
+
+const XK_CONTROL_L: u32 = 0xffe3;
+const XK_C: u32 = 0x0063;
+const XK_V: u32 = 0x0076;
+
+x11rb::atom_manager! {
+    Atoms: AtomsCookie {
+        CLIPBOARD,
+        TARGETS,
+        MULTIPLE,
+        TIMESTAMP,
+        SAVE_TARGETS,
+        DELETE,
+        TEXT,
+        UTF8_STRING,
+        TEXT_PLAIN_UTF8: b"text/plain;charset=utf-8",
+        TEXT_HTML: b"text/html",
+        INCR,
+        _NET_ACTIVE_WINDOW,
+        REPHRASER_SELECTION,
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum Selection {
+    /// Whatever text is currently highlighted.
+    Primary,
+    /// What was last copied with Ctrl+C.
+    Clipboard,
+}
+
+pub fn is_available() -> bool {
+    std::env::var_os("DISPLAY").is_some_and(|display| !display.is_empty())
+}
+
+/// Reads a selection as text, or `None` when nobody owns it or the owner
+/// offers no text flavor.
+pub fn read_selection(selection: Selection) -> Result<Option<String>, Box<dyn Error>> {
+    let session = Session::connect()?;
+    let selection = session.selection_atom(selection);
+
+    if session.conn.get_selection_owner(selection)?.reply()?.owner == NONE {
+        return Ok(None);
+    }
+
+    if let Some(bytes) = session.convert(selection, session.atoms.UTF8_STRING)? {
+        return Ok(Some(String::from_utf8_lossy(&bytes).into_owned()));
+    }
+    // Older toolkits only offer Latin-1 STRING
+    if let Some(bytes) = session.convert(selection, AtomEnum::STRING.into())? {
+        return Ok(Some(bytes.iter().map(|&b| b as char).collect()));
+    }
+    Ok(None)
+}
+
+/// The text/html target of `selection`, undecoded: Firefox sends UTF-16.
+pub fn read_html(selection: Selection) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+    let session = Session::connect()?;
+    let selection = session.selection_atom(selection);
+
+    if session.conn.get_selection_owner(selection)?.reply()?.owner == NONE {
+        return Ok(None);
+    }
+    session.convert(selection, session.atoms.TEXT_HTML)
+}
+
+/// Takes ownership of CLIPBOARD and serves `text` from a background thread
+/// until another client copies something.
+pub fn write_clipboard(text: &str) -> Result<(), Box<dyn Error>> {
+    let session = Session::connect()?;
+    let contents = [
+        session.atoms.UTF8_STRING,
+        session.atoms.TEXT_PLAIN_UTF8,
+        session.atoms.TEXT,
+        AtomEnum::STRING.into(),
+    ]
+    .into_iter()
+    .map(|target| (target, text.as_bytes().to_vec()))
+    .collect();
+    session.own_clipboard(contents)
+}
+
+/// Every target the CLIPBOARD owner offers, keyed by atom name, so it can be
+/// served again later with [`write_clipboard_contents`].
+pub fn snapshot_clipboard() -> Result<Flavors, Box<dyn Error>> {
+    let session = Session::connect()?;
+    let selection = session.atoms.CLIPBOARD;
+    if session.conn.get_selection_owner(selection)?.reply()?.owner == NONE {
+        return Ok(Vec::new());
+    }
+
+    let Some(targets) = session.convert(selection, session.atoms.TARGETS)? else {
+        return Ok(Vec::new());
+    };
+    // Meta targets describe the selection rather than hold data
+    let meta = [
+        session.atoms.TARGETS,
+        session.atoms.MULTIPLE,
+        session.atoms.TIMESTAMP,
+        session.atoms.SAVE_TARGETS,
+        session.atoms.DELETE,
+    ];
+
+    let mut flavors = Vec::new();
+    for target in targets
+        .chunks_exact(4)
+        .map(|bytes| u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .filter(|target| !meta.contains(target))
+    {
+        let name = String::from_utf8_lossy(&session.conn.get_atom_name(target)?.reply()?.name)
+            .into_owned();
+        match session.convert(selection, target) {
+            Ok(Some(bytes)) => flavors.push((name, bytes)),
+            Ok(None) => {}
+            Err(e) => eprintln!("⚠️  Skipping clipboard target {}: {}", name, e),
+        }
+    }
+    Ok(flavors)
+}
+
+/// Serves exactly `flavors` on CLIPBOARD, as they were snapshotted.
+pub fn write_clipboard_contents(flavors: &[(String, Vec<u8>)]) -> Result<(), Box<dyn Error>> {
+    let session = Session::connect()?;
+    let mut contents = Vec::with_capacity(flavors.len());
+    for (name, bytes) in flavors {
+        let target = session.conn.intern_atom(false, name.as_bytes())?.reply()?.atom;
+        contents.push((target, bytes.clone()));
+    }
+    session.own_clipboard(contents)
+}
+
+/// Owner and acquisition time of CLIPBOARD, packed into one value that
+/// changes whenever anyone copies, even from the same window.
+pub fn clipboard_generation() -> Result<u64, Box<dyn Error>> {
+    let mut cached = GENERATION_SESSION.lock().unwrap_or_else(PoisonError::into_inner);
+    let session = match cached.take() {
+        Some(session) => session,
+        None => Session::connect()?,
+    };
+
+    let selection = session.atoms.CLIPBOARD;
+    let owner = session.conn.get_selection_owner(selection)?.reply()?.owner;
+    if owner == NONE {
+        *cached = Some(session);
+        return Ok(0);
+    }
+
+    // Owners that don't answer TIMESTAMP are tracked by window alone. A failed
+    // conversion may still be answered later, so that connection is dropped
+    // rather than reused with a stray notification in its queue.
+    let timestamp = match session.convert(selection, session.atoms.TIMESTAMP) {
+        Ok(bytes) => {
+            *cached = Some(session);
+            bytes.and_then(|bytes| bytes.get(..4).map(|b| u32::from_ne_bytes([b[0], b[1], b[2], b[3]])))
+        }
+        Err(_) => None,
+    }
+    .unwrap_or(0);
+    Ok((u64::from(owner) << 32) | u64::from(timestamp))
+}
+
+/// The window that currently has focus, per the window manager.
+pub fn active_window() -> Result<Option<Window>, Box<dyn Error>> {
+    let session = Session::connect()?;
+    let reply = session
+        .conn
+        .get_property(
+            false,
+            session.root,
+            session.atoms._NET_ACTIVE_WINDOW,
+            AtomEnum::WINDOW,
+            0,
+            1,
+        )?
+        .reply()?;
+    Ok(reply
+        .value32()
+        .and_then(|mut values| values.next())
+        .filter(|&window| window != NONE))
+}
+
+/// Application class of `window` from WM_CLASS, e.g. "Firefox" or "Slack".
+pub fn window_class(window: Window) -> Result<Option<String>, Box<dyn Error>> {
+    let session = Session::connect()?;
+    let reply = session
+        .conn
+        .get_property(false, window, AtomEnum::WM_CLASS, AtomEnum::STRING, 0, 256)?
+        .reply()?;
+    // Two NUL-terminated strings: the instance name, then the class
+    Ok(reply
+        .value
+        .split(|&byte| byte == 0)
+        .nth(1)
+        .filter(|class| !class.is_empty())
+        .map(|class| String::from_utf8_lossy(class).into_owned()))
+}
+
+/// Asks the window manager to raise and focus `window`.
+pub fn activate_window(window: Window) -> Result<(), Box<dyn Error>> {
+    let session = Session::connect()?;
+    // Source indication 2 (pager) keeps focus-stealing prevention from ignoring us
+    let event = ClientMessageEvent::new(
+        32,
+        window,
+        session.atoms._NET_ACTIVE_WINDOW,
+        [2, CURRENT_TIME, 0, 0, 0],
+    );
+    session.conn.send_event(
+        false,
+        session.root,
+        EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+        event,
+    )?;
+    session.conn.get_input_focus()?.reply()?;
+    Ok(())
+}
+
+/// Sends Ctrl+C to the focused window through the XTest extension.
+pub fn simulate_copy() -> Result<(), Box<dyn Error>> {
+    Session::connect()?.press_with_control(XK_C)
+}
+
+/// Sends Ctrl+V to the focused window through the XTest extension.
+pub fn simulate_paste() -> Result<(), Box<dyn Error>> {
+    Session::connect()?.press_with_control(XK_V)
+}
+
+struct Session {
+    conn: RustConnection,
+    root: Window,
+    window: Window,
+    atoms: Atoms,
+}
+
+impl Session {
+    fn connect() -> Result<Self, Box<dyn Error>> {
+        let (conn, screen_num) = x11rb::connect(None)
+            .map_err(|e| format!("Failed to connect to the X server: {}", e))?;
+        let root = conn.setup().roots[screen_num].root;
+
+        // Invisible window that receives the converted selection
+        let window = conn.generate_id()?;
+        conn.create_window(
+            COPY_DEPTH_FROM_PARENT,
+            window,
+            root,
+            0,
+            0,
+            1,
+            1,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            COPY_FROM_PARENT,
+            &CreateWindowAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+        )?;
+        let atoms = Atoms::new(&conn)?.reply()?;
+
+        Ok(Self { conn, root, window, atoms })
+    }
+
+    fn selection_atom(&self, selection: Selection) -> Atom {
+        match selection {
+            Selection::Primary => AtomEnum::PRIMARY.into(),
+            Selection::Clipboard => self.atoms.CLIPBOARD,
+        }
+    }
+
+    // Asks the owner to convert `selection` to `target`; `None` when it refuses
+    fn convert(&self, selection: Atom, target: Atom) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        let property = self.atoms.REPHRASER_SELECTION;
+        self.conn
+            .convert_selection(self.window, selection, target, property, CURRENT_TIME)?;
+        self.conn.flush()?;
+
+        let notify = match self.wait_for(|event| {
+            matches!(event, Event::SelectionNotify(n) if n.selection == selection)
+        })? {
+            Some(Event::SelectionNotify(notify)) => notify,
+            _ => return Err("Timed out waiting for the selection owner to respond".into()),
+        };
+        if notify.property == NONE {
+            return Ok(None);
+        }
+
+        let reply = self
+            .conn
+            .get_property(true, self.window, property, AtomEnum::ANY, 0, u32::MAX)?
+            .reply()?;
+        if reply.type_ != self.atoms.INCR {
+            return Ok(Some(reply.value));
+        }
+
+        // Large selections arrive in chunks; an empty chunk ends the transfer
+        let mut data = Vec::new();
+        loop {
+            self.wait_for(|event| {
+                matches!(event, Event::PropertyNotify(p)
+                    if p.atom == property && p.state == Property::NEW_VALUE)
+            })?
+            .ok_or("Timed out during an incremental selection transfer")?;
+
+            let chunk = self
+                .conn
+                .get_property(true, self.window, property, AtomEnum::ANY, 0, u32::MAX)?
+                .reply()?;
+            if chunk.value.is_empty() {
+                return Ok(Some(data));
+            }
+            data.extend_from_slice(&chunk.value);
+        }
+    }
+
+    // Takes CLIPBOARD and serves `contents` from a background thread
+    fn own_clipboard(self, contents: Vec<(Atom, Vec<u8>)>) -> Result<(), Box<dyn Error>> {
+        let selection = self.atoms.CLIPBOARD;
+        self.conn
+            .set_selection_owner(self.window, selection, CURRENT_TIME)?;
+        if self.conn.get_selection_owner(selection)?.reply()?.owner != self.window {
+            return Err("Failed to take ownership of the clipboard".into());
+        }
+
+        std::thread::spawn(move || {
+            if let Err(e) = self.serve(selection, &contents) {
+                eprintln!("⚠️  Clipboard owner stopped: {}", e);
+            }
+        });
+        Ok(())
+    }
+
+    // Answers paste requests until another client takes the selection
+    fn serve(&self, selection: Atom, contents: &[(Atom, Vec<u8>)]) -> Result<(), Box<dyn Error>> {
+        loop {
+            match self.conn.wait_for_event()? {
+                Event::SelectionClear(event) if event.selection == selection => return Ok(()),
+                Event::SelectionRequest(request) => self.answer(&request, contents)?,
+                _ => {}
+            }
+        }
+    }
+
+    fn answer(
+        &self,
+        request: &SelectionRequestEvent,
+        contents: &[(Atom, Vec<u8>)],
+    ) -> Result<(), Box<dyn Error>> {
+        // Obsolete clients leave the property unset and expect the target name
+        let property = if request.property == NONE {
+            request.target
+        } else {
+            request.property
+        };
+
+        let stored = if request.target == self.atoms.TARGETS {
+            let targets: Vec<Atom> = std::iter::once(self.atoms.TARGETS)
+                .chain(contents.iter().map(|(target, _)| *target))
+                .collect();
+            self.conn.change_property32(
+                PropMode::REPLACE,
+                request.requestor,
+                property,
+                AtomEnum::ATOM,
+                &targets,
+            )?;
+            true
+        } else if let Some((target, data)) = contents.iter().find(|(target, data)| {
+            // Anything bigger would need an INCR transfer; refuse rather than fail the request
+            *target == request.target && data.len() + 64 < self.conn.maximum_request_bytes()
+        }) {
+            self.conn.change_property8(
+                PropMode::REPLACE,
+                request.requestor,
+                property,
+                *target,
+                data,
+            )?;
+            true
+        } else {
+            false
+        };
+
+        let notify = SelectionNotifyEvent {
+            response_type: SELECTION_NOTIFY_EVENT,
+            sequence: 0,
+            time: request.time,
+            requestor: request.requestor,
+            selection: request.selection,
+            target: request.target,
+            property: if stored { property } else { NONE },
+        };
+        self.conn
+            .send_event(false, request.requestor, EventMask::NO_EVENT, notify)?;
+        self.conn.flush()?;
+        Ok(())
+    }
+
+    fn wait_for(&self, wanted: impl Fn(&Event) -> bool) -> Result<Option<Event>, Box<dyn Error>> {
+        let deadline = Instant::now() + SELECTION_TIMEOUT;
+        while Instant::now() < deadline {
+            match self.conn.poll_for_event()? {
+                Some(event) if wanted(&event) => return Ok(Some(event)),
+                Some(_) => {}
+                None => std::thread::sleep(Duration::from_millis(5)),
+            }
+        }
+        Ok(None)
+    }
+
+    fn press_with_control(&self, keysym: u32) -> Result<(), Box<dyn Error>> {
+        if self
+            .conn
+            .extension_information(xtest::X11_EXTENSION_NAME)?
+            .is_none()
+        {
+            return Err("The X server does not support the XTest extension".into());
+        }
+
+        let control = self.keycode_for(XK_CONTROL_L)?;
+        let key = self.keycode_for(keysym)?;
+
+        for (event_type, keycode) in [
+            (KEY_PRESS_EVENT, control),
+            (KEY_PRESS_EVENT, key),
+            (KEY_RELEASE_EVENT, key),
+            (KEY_RELEASE_EVENT, control),
+        ] {
+            self.conn
+                .xtest_fake_input(event_type, keycode, CURRENT_TIME, self.root, 0, 0, 0)?;
+        }
+        // Round trip so the events are processed before the connection closes
+        self.conn.get_input_focus()?.reply()?;
+        Ok(())
+    }
+
+    fn keycode_for(&self, keysym: u32) -> Result<u8, Box<dyn Error>> {
+        let setup = self.conn.setup();
+        let (min, max) = (setup.min_keycode, setup.max_keycode);
+        let mapping = self
+            .conn
+            .get_keyboard_mapping(min, max - min + 1)?
+            .reply()?;
+
+        let per_keycode = usize::from(mapping.keysyms_per_keycode).max(1);
+        mapping
+            .keysyms
+            .chunks(per_keycode)
+            .position(|keysyms| keysyms.contains(&keysym))
+            .map(|index| min + index as u8)
+            .ok_or_else(|| format!("No key is mapped to keysym {:#x}", keysym).into())
+    }
+}
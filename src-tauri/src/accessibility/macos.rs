@@ -0,0 +1,394 @@
+// Pasteboard and Cmd+C/Cmd+V through Cocoa and Quartz events
+use super::{ClipboardBackend, SelectionSource};
+use std::error::Error;
+
+// Pasteboard items, each a list of (UTI, bytes)
+type ClipboardSnapshot = super::ClipboardSnapshot<String>;
+
+/// The general pasteboard and the frontmost app.
+pub struct Native;
+
+impl ClipboardBackend for Native {
+    type Format = String;
+
+    fn read_text(&self) -> Result<Option<String>, Box<dyn Error>> {
+        read_clipboard_text()
+    }
+
+    fn write_text(&self, text: &str) -> Result<(), Box<dyn Error>> {
+        write_clipboard_text(text)
+    }
+
+    fn read_html(&self) -> Result<Option<String>, Box<dyn Error>> {
+        read_clipboard_html()
+    }
+
+    fn write_rich(&self, text: &str, html: &str) -> Result<(), Box<dyn Error>> {
+        write_clipboard_rich(text, html)
+    }
+
+    fn snapshot(&self) -> Result<ClipboardSnapshot, Box<dyn Error>> {
+        snapshot_clipboard()
+    }
+
+    fn write(&self, snapshot: &ClipboardSnapshot) -> Result<(), Box<dyn Error>> {
+        write_clipboard(snapshot)
+    }
+
+    fn change_count(&self) -> Result<u64, Box<dyn Error>> {
+        clipboard_change_count()
+    }
+}
+
+impl SelectionSource for Native {
+    fn synthesize_copy(&self) -> Result<(), Box<dyn Error>> {
+        eprintln!("⌨️  Simulating Cmd+C...");
+        simulate_copy_command()
+    }
+
+    fn synthesize_paste(&self) -> Result<(), Box<dyn Error>> {
+        simulate_paste()
+    }
+
+    fn focused_window(&self) -> Option<isize> {
+        active_window()
+    }
+
+    fn focus_window(&self, pid: isize) -> Result<(), Box<dyn Error>> {
+        activate_window(pid)
+    }
+
+    fn app_name(&self, pid: isize) -> Option<String> {
+        app_name(pid)
+    }
+}
+
+fn read_clipboard_text() -> Result<Option<String>, Box<dyn Error>> {
+    use cocoa::appkit::NSPasteboardTypeString;
+    use cocoa::base::{id, nil};
+    use cocoa::foundation::{NSAutoreleasePool, NSString};
+    use objc::{class, msg_send, sel, sel_impl};
+
+    unsafe {
+        let _pool = NSAutoreleasePool::new(nil);
+
+        let pasteboard: id = msg_send![class!(NSPasteboard), generalPasteboard];
+        let contents: id = msg_send![pasteboard, stringForType: NSPasteboardTypeString];
+        if contents == nil {
+            return Ok(None);
+        }
+
+        let c_str = NSString::UTF8String(contents);
+        if c_str.is_null() {
+            eprintln!("❌ Failed to convert clipboard to UTF8 string");
+            return Err("Failed to read copied text from clipboard.".into());
+        }
+        Ok(Some(std::ffi::CStr::from_ptr(c_str).to_string_lossy().into_owned()))
+    }
+}
+
+fn write_clipboard_text(text: &str) -> Result<(), Box<dyn Error>> {
+    use cocoa::appkit::NSPasteboardTypeString;
+    use cocoa::base::{id, nil};
+    use cocoa::foundation::{NSAutoreleasePool, NSString};
+    use objc::{class, msg_send, sel, sel_impl};
+
+    unsafe {
+        let _pool = NSAutoreleasePool::new(nil);
+
+        let pasteboard: id = msg_send![class!(NSPasteboard), generalPasteboard];
+        let ns_string = NSString::alloc(nil);
+        let ns_string = NSString::init_str(ns_string, text);
+        let _: () = msg_send![pasteboard, clearContents];
+        let _: () = msg_send![pasteboard, setString:ns_string forType:NSPasteboardTypeString];
+    }
+    Ok(())
+}
+
+// HTML from browsers, Google Docs and Outlook. Pages, TextEdit and Notes only
+// write RTF, which AppKit can render as HTML for us.
+fn read_clipboard_html() -> Result<Option<String>, Box<dyn Error>> {
+    use cocoa::base::{id, nil};
+    use cocoa::foundation::{NSAutoreleasePool, NSRange, NSString};
+    use objc::{class, msg_send, sel, sel_impl};
+
+    unsafe {
+        let _pool = NSAutoreleasePool::new(nil);
+
+        let pasteboard: id = msg_send![class!(NSPasteboard), generalPasteboard];
+        let html_type = NSString::alloc(nil);
+        let html_type = NSString::init_str(html_type, "public.html");
+        let html: id = msg_send![pasteboard, stringForType: html_type];
+        if html != nil {
+            return Ok(ns_string_to_string(html));
+        }
+
+        let rtf_type = NSString::alloc(nil);
+        let rtf_type = NSString::init_str(rtf_type, "public.rtf");
+        let rtf: id = msg_send![pasteboard, dataForType: rtf_type];
+        if rtf == nil {
+            return Ok(None);
+        }
+
+        let attributed: id = msg_send![class!(NSAttributedString), alloc];
+        let attributed: id = msg_send![attributed, initWithRTF: rtf documentAttributes: nil];
+        if attributed == nil {
+            return Ok(None);
+        }
+        let length: usize = msg_send![attributed, length];
+
+        // NSDocumentTypeDocumentAttribute: NSHTMLTextDocumentType
+        let key = NSString::alloc(nil);
+        let key = NSString::init_str(key, "DocumentType");
+        let value = NSString::alloc(nil);
+        let value = NSString::init_str(value, "NSHTML");
+        let attributes: id = msg_send![class!(NSDictionary), dictionaryWithObject: value forKey: key];
+
+        let range = NSRange::new(0, length as u64);
+        let error: *mut id = std::ptr::null_mut();
+        let data: id = msg_send![attributed, dataFromRange: range documentAttributes: attributes error: error];
+        let _: () = msg_send![attributed, release];
+        if data == nil {
+            return Ok(None);
+        }
+
+        let length: usize = msg_send![data, length];
+        let bytes: *const u8 = msg_send![data, bytes];
+        if length == 0 || bytes.is_null() {
+            return Ok(None);
+        }
+        let html = std::slice::from_raw_parts(bytes, length);
+        Ok(Some(String::from_utf8_lossy(html).into_owned()))
+    }
+}
+
+fn write_clipboard_rich(text: &str, html: &str) -> Result<(), Box<dyn Error>> {
+    use cocoa::appkit::NSPasteboardTypeString;
+    use cocoa::base::{id, nil};
+    use cocoa::foundation::{NSAutoreleasePool, NSString};
+    use objc::{class, msg_send, sel, sel_impl};
+
+    // Without a charset, WebKit reads the fragment as Latin-1
+    let html = format!("<meta charset=\"utf-8\">{}", html);
+
+    unsafe {
+        let _pool = NSAutoreleasePool::new(nil);
+
+        let pasteboard: id = msg_send![class!(NSPasteboard), generalPasteboard];
+        let ns_text = NSString::alloc(nil);
+        let ns_text = NSString::init_str(ns_text, text);
+        let ns_html = NSString::alloc(nil);
+        let ns_html = NSString::init_str(ns_html, &html);
+        let html_type = NSString::alloc(nil);
+        let html_type = NSString::init_str(html_type, "public.html");
+
+        let _: isize = msg_send![pasteboard, clearContents];
+        let _: bool = msg_send![pasteboard, setString:ns_text forType:NSPasteboardTypeString];
+        let written: bool = msg_send![pasteboard, setString:ns_html forType:html_type];
+        if !written {
+            return Err("Failed to write to the clipboard".into());
+        }
+    }
+    Ok(())
+}
+
+unsafe fn ns_string_to_string(string: cocoa::base::id) -> Option<String> {
+    use cocoa::foundation::NSString;
+
+    let c_str = NSString::UTF8String(string);
+    if c_str.is_null() {
+        return None;
+    }
+    Some(std::ffi::CStr::from_ptr(c_str).to_string_lossy().into_owned())
+}
+
+fn snapshot_clipboard() -> Result<ClipboardSnapshot, Box<dyn Error>> {
+    use cocoa::base::{id, nil};
+    use cocoa::foundation::{NSAutoreleasePool, NSString};
+    use objc::{class, msg_send, sel, sel_impl};
+
+    unsafe {
+        let _pool = NSAutoreleasePool::new(nil);
+
+        let pasteboard: id = msg_send![class!(NSPasteboard), generalPasteboard];
+        let items: id = msg_send![pasteboard, pasteboardItems];
+        let mut snapshot = ClipboardSnapshot::default();
+        if items == nil {
+            return Ok(snapshot);
+        }
+
+        let item_count: usize = msg_send![items, count];
+        for i in 0..item_count {
+            let item: id = msg_send![items, objectAtIndex: i];
+            let types: id = msg_send![item, types];
+            let type_count: usize = msg_send![types, count];
+
+            let mut flavors = Vec::with_capacity(type_count);
+            for j in 0..type_count {
+                let pb_type: id = msg_send![types, objectAtIndex: j];
+                let data: id = msg_send![item, dataForType: pb_type];
+                let c_str = NSString::UTF8String(pb_type);
+                // Promised data that never arrives (e.g. a quit app) has no bytes
+                if data == nil || c_str.is_null() {
+                    continue;
+                }
+
+                let length: usize = msg_send![data, length];
+                let bytes: *const u8 = msg_send![data, bytes];
+                let bytes = if length == 0 || bytes.is_null() {
+                    Vec::new()
+                } else {
+                    std::slice::from_raw_parts(bytes, length).to_vec()
+                };
+                let format = std::ffi::CStr::from_ptr(c_str).to_string_lossy().into_owned();
+                flavors.push((format, bytes));
+            }
+            snapshot.items.push(flavors);
+        }
+        Ok(snapshot)
+    }
+}
+
+fn write_clipboard(snapshot: &ClipboardSnapshot) -> Result<(), Box<dyn Error>> {
+    use cocoa::base::{id, nil};
+    use cocoa::foundation::{NSAutoreleasePool, NSString};
+    use objc::{class, msg_send, sel, sel_impl};
+
+    unsafe {
+        let _pool = NSAutoreleasePool::new(nil);
+
+        let objects: id = msg_send![class!(NSMutableArray), array];
+        for flavors in &snapshot.items {
+            let item: id = msg_send![class!(NSPasteboardItem), new];
+            for (format, bytes) in flavors {
+                let pb_type = NSString::alloc(nil);
+                let pb_type = NSString::init_str(pb_type, format);
+                let data: id = msg_send![
+                    class!(NSData),
+                    dataWithBytes: bytes.as_ptr()
+                    length: bytes.len()
+                ];
+                let _: bool = msg_send![item, setData:data forType:pb_type];
+            }
+            let _: () = msg_send![objects, addObject: item];
+            let _: () = msg_send![item, release];
+        }
+
+        let pasteboard: id = msg_send![class!(NSPasteboard), generalPasteboard];
+        let _: isize = msg_send![pasteboard, clearContents];
+        let written: bool = msg_send![pasteboard, writeObjects: objects];
+        if !written {
+            return Err("Failed to write to the clipboard".into());
+        }
+    }
+    Ok(())
+}
+
+fn clipboard_change_count() -> Result<u64, Box<dyn Error>> {
+    use cocoa::base::id;
+    use objc::{class, msg_send, sel, sel_impl};
+
+    unsafe {
+        let pasteboard: id = msg_send![class!(NSPasteboard), generalPasteboard];
+        let count: isize = msg_send![pasteboard, changeCount];
+        Ok(count as u64)
+    }
+}
+
+// Process id of the frontmost app; windows of other apps never take key focus from a paste
+fn active_window() -> Option<isize> {
+    use cocoa::base::{id, nil};
+    use objc::{class, msg_send, sel, sel_impl};
+
+    unsafe {
+        let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let app: id = msg_send![workspace, frontmostApplication];
+        if app == nil {
+            return None;
+        }
+        let pid: i32 = msg_send![app, processIdentifier];
+        Some(pid as isize)
+    }
+}
+
+fn app_name(pid: isize) -> Option<String> {
+    use cocoa::base::{id, nil};
+    use objc::{class, msg_send, sel, sel_impl};
+
+    unsafe {
+        let app: id = msg_send![
+            class!(NSRunningApplication),
+            runningApplicationWithProcessIdentifier: pid as i32
+        ];
+        if app == nil {
+            return None;
+        }
+        let name: id = msg_send![app, localizedName];
+        ns_string_to_string(name)
+    }
+}
+
+fn activate_window(pid: isize) -> Result<(), Box<dyn Error>> {
+    use cocoa::base::{id, nil};
+    use objc::{class, msg_send, sel, sel_impl};
+
+    // NSApplicationActivateIgnoringOtherApps
+    const ACTIVATE_IGNORING_OTHER_APPS: u64 = 1 << 1;
+
+    unsafe {
+        let app: id = msg_send![
+            class!(NSRunningApplication),
+            runningApplicationWithProcessIdentifier: pid as i32
+        ];
+        if app == nil {
+            return Err("The app the text was selected in is no longer running.".into());
+        }
+        let _: bool = msg_send![app, activateWithOptions: ACTIVATE_IGNORING_OTHER_APPS];
+    }
+    Ok(())
+}
+
+fn simulate_copy_command() -> Result<(), Box<dyn Error>> {
+    // Key code for 'C' is 8
+    simulate_command_key(8, "Cmd+C")
+}
+
+fn simulate_paste() -> Result<(), Box<dyn Error>> {
+    // Key code for 'V' is 9
+    simulate_command_key(9, "Cmd+V")
+}
+
+fn simulate_command_key(
+    key_code: core_graphics::event::CGKeyCode,
+    label: &str,
+) -> Result<(), Box<dyn Error>> {
+    use core_graphics::event::{CGEvent, CGEventFlags, CGEventTapLocation};
+    use core_graphics::event_source::{CGEventSource, CGEventSourceStateID};
+
+    eprintln!("🎯 Creating event source...");
+    let source = CGEventSource::new(CGEventSourceStateID::CombinedSessionState)
+        .map_err(|_| {
+            eprintln!("❌ Failed to create event source - accessibility permissions may be missing!");
+            "Failed to create event source. Please grant accessibility permissions in System Preferences → Security & Privacy → Accessibility."
+        })?;
+    eprintln!("✅ Event source created");
+
+    eprintln!("⌨️  Creating {} key events...", label);
+    // Create key down event with Cmd modifier
+    let key_down = CGEvent::new_keyboard_event(source.clone(), key_code, true)
+        .map_err(|_| "Failed to create key down event")?;
+    key_down.set_flags(CGEventFlags::CGEventFlagCommand);
+
+    // Create key up event with Cmd modifier
+    let key_up = CGEvent::new_keyboard_event(source, key_code, false)
+        .map_err(|_| "Failed to create key up event")?;
+    key_up.set_flags(CGEventFlags::CGEventFlagCommand);
+
+    eprintln!("📤 Posting {} events...", label);
+    // Post the events
+    key_down.post(CGEventTapLocation::HID);
+    key_up.post(CGEventTapLocation::HID);
+    eprintln!("✅ {} events posted", label);
+
+    Ok(())
+}
@@ -0,0 +1,191 @@
+// A clipboard and a source app held in memory, standing in for the desktop
+use super::{CapturedText, ClipboardBackend, ClipboardSnapshot, SelectionSource};
+use std::error::Error;
+use std::sync::Mutex;
+
+/// Formats the plain and rich flavors are stored under.
+pub const TEXT_FORMAT: &str = "text/plain";
+pub const HTML_FORMAT: &str = "text/html";
+
+/// Fake desktop with one clipboard and one app holding a selection.
+/// Copying puts the selection on the clipboard like a real app would;
+/// pastes are recorded instead of typed.
+#[derive(Default)]
+pub struct MemoryDesktop {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    clipboard: ClipboardSnapshot<String>,
+    change_count: u64,
+    selection: Option<CapturedText>,
+    primary: Option<CapturedText>,
+    ignores_copy: bool,
+    focused: Option<isize>,
+    pasted: Vec<String>,
+    copy_after_paste: Option<ClipboardSnapshot<String>>,
+}
+
+impl MemoryDesktop {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Highlights `text` in the source app.
+    pub fn select(&self, text: &str) {
+        self.state.lock().unwrap().selection = Some(CapturedText { text: text.to_string(), html: None });
+    }
+
+    /// Highlights formatted text, which copies with an HTML flavor.
+    pub fn select_rich(&self, text: &str, html: &str) {
+        self.state.lock().unwrap().selection = Some(CapturedText {
+            text: text.to_string(),
+            html: Some(html.to_string()),
+        });
+    }
+
+    /// Sets the PRIMARY selection buffer, as X11 and Wayland apps do on highlight.
+    pub fn set_primary(&self, text: &str) {
+        self.state.lock().unwrap().primary = Some(CapturedText { text: text.to_string(), html: None });
+    }
+
+    /// Makes the source app swallow the copy shortcut without touching the clipboard.
+    pub fn ignore_copy(&self, ignores: bool) {
+        self.state.lock().unwrap().ignores_copy = ignores;
+    }
+
+    /// Makes another app copy `snapshot` right after the next paste, as
+    /// clipboard managers and sync tools do.
+    pub fn copy_after_paste(&self, snapshot: ClipboardSnapshot<String>) {
+        self.state.lock().unwrap().copy_after_paste = Some(snapshot);
+    }
+
+    /// Replaces the clipboard, as another app copying would.
+    pub fn set_clipboard(&self, snapshot: ClipboardSnapshot<String>) {
+        let mut state = self.state.lock().unwrap();
+        state.clipboard = snapshot;
+        state.change_count += 1;
+    }
+
+    pub fn clipboard(&self) -> ClipboardSnapshot<String> {
+        self.state.lock().unwrap().clipboard.clone()
+    }
+
+    pub fn focused(&self) -> Option<isize> {
+        self.state.lock().unwrap().focused
+    }
+
+    /// Clipboard text at the time of each paste, oldest first.
+    pub fn pasted(&self) -> Vec<String> {
+        self.state.lock().unwrap().pasted.clone()
+    }
+}
+
+impl ClipboardBackend for MemoryDesktop {
+    type Format = String;
+
+    fn read_text(&self) -> Result<Option<String>, Box<dyn Error>> {
+        Ok(flavor_of(&self.state.lock().unwrap().clipboard, TEXT_FORMAT))
+    }
+
+    fn write_text(&self, text: &str) -> Result<(), Box<dyn Error>> {
+        self.set_clipboard(text_snapshot(text));
+        Ok(())
+    }
+
+    fn read_html(&self) -> Result<Option<String>, Box<dyn Error>> {
+        Ok(flavor_of(&self.state.lock().unwrap().clipboard, HTML_FORMAT))
+    }
+
+    fn write_rich(&self, text: &str, html: &str) -> Result<(), Box<dyn Error>> {
+        self.set_clipboard(rich_snapshot(text, html));
+        Ok(())
+    }
+
+    fn snapshot(&self) -> Result<ClipboardSnapshot<String>, Box<dyn Error>> {
+        Ok(self.clipboard())
+    }
+
+    fn write(&self, snapshot: &ClipboardSnapshot<String>) -> Result<(), Box<dyn Error>> {
+        self.set_clipboard(snapshot.clone());
+        Ok(())
+    }
+
+    fn change_count(&self) -> Result<u64, Box<dyn Error>> {
+        Ok(self.state.lock().unwrap().change_count)
+    }
+}
+
+impl SelectionSource for MemoryDesktop {
+    fn read_selection(&self) -> Result<Option<CapturedText>, Box<dyn Error>> {
+        Ok(self.state.lock().unwrap().primary.clone())
+    }
+
+    // Apps leave the clipboard alone when nothing is selected
+    fn synthesize_copy(&self) -> Result<(), Box<dyn Error>> {
+        let state = self.state.lock().unwrap();
+        if state.ignores_copy {
+            return Ok(());
+        }
+        let Some(selection) = state.selection.clone() else {
+            return Ok(());
+        };
+        drop(state);
+        match selection.html {
+            Some(html) => self.set_clipboard(rich_snapshot(&selection.text, &html)),
+            None => self.set_clipboard(text_snapshot(&selection.text)),
+        }
+        Ok(())
+    }
+
+    fn synthesize_paste(&self) -> Result<(), Box<dyn Error>> {
+        let mut state = self.state.lock().unwrap();
+        let text = flavor_of(&state.clipboard, TEXT_FORMAT).unwrap_or_default();
+        state.selection = Some(CapturedText {
+            text: text.clone(),
+            html: flavor_of(&state.clipboard, HTML_FORMAT),
+        });
+        state.pasted.push(text);
+        if let Some(snapshot) = state.copy_after_paste.take() {
+            state.clipboard = snapshot;
+            state.change_count += 1;
+        }
+        Ok(())
+    }
+
+    fn focused_window(&self) -> Option<isize> {
+        self.focused()
+    }
+
+    fn focus_window(&self, window: isize) -> Result<(), Box<dyn Error>> {
+        self.state.lock().unwrap().focused = Some(window);
+        Ok(())
+    }
+}
+
+/// A clipboard holding only `text`.
+pub fn text_snapshot(text: &str) -> ClipboardSnapshot<String> {
+    ClipboardSnapshot {
+        items: vec![vec![(TEXT_FORMAT.to_string(), text.as_bytes().to_vec())]],
+    }
+}
+
+/// A clipboard holding `text` with `html` as its rich flavor.
+pub fn rich_snapshot(text: &str, html: &str) -> ClipboardSnapshot<String> {
+    ClipboardSnapshot {
+        items: vec![vec![
+            (TEXT_FORMAT.to_string(), text.as_bytes().to_vec()),
+            (HTML_FORMAT.to_string(), html.as_bytes().to_vec()),
+        ]],
+    }
+}
+
+fn flavor_of(snapshot: &ClipboardSnapshot<String>, wanted: &str) -> Option<String> {
+    snapshot
+        .items
+        .iter()
+        .flatten()
+        .find(|(format, _)| format == wanted)
+        .map(|(_, bytes)| String::from_utf8_lossy(bytes).into_owned())
+}
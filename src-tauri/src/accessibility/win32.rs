@@ -0,0 +1,373 @@
+// Win32 clipboard and Ctrl+C/Ctrl+V through SendInput
+use super::{ClipboardBackend, SelectionSource};
+use std::error::Error;
+use std::time::Duration;
+
+// A single item of (clipboard format id, bytes)
+type ClipboardSnapshot = super::ClipboardSnapshot<u32>;
+
+/// The system clipboard and the foreground window.
+pub struct Native;
+
+impl ClipboardBackend for Native {
+    type Format = u32;
+
+    fn read_text(&self) -> Result<Option<String>, Box<dyn Error>> {
+        read_clipboard_text()
+    }
+
+    fn write_text(&self, text: &str) -> Result<(), Box<dyn Error>> {
+        write_clipboard_text(text)
+    }
+
+    fn read_html(&self) -> Result<Option<String>, Box<dyn Error>> {
+        read_clipboard_html()
+    }
+
+    fn write_rich(&self, text: &str, html: &str) -> Result<(), Box<dyn Error>> {
+        write_clipboard(&ClipboardSnapshot {
+            items: vec![vec![
+                (CF_UNICODETEXT, utf16_bytes(text)),
+                (html_format()?, cf_html(html).into_bytes()),
+            ]],
+        })
+    }
+
+    fn snapshot(&self) -> Result<ClipboardSnapshot, Box<dyn Error>> {
+        snapshot_clipboard()
+    }
+
+    fn write(&self, snapshot: &ClipboardSnapshot) -> Result<(), Box<dyn Error>> {
+        write_clipboard(snapshot)
+    }
+
+    fn change_count(&self) -> Result<u64, Box<dyn Error>> {
+        clipboard_change_count()
+    }
+}
+
+impl SelectionSource for Native {
+    fn synthesize_copy(&self) -> Result<(), Box<dyn Error>> {
+        use windows::Win32::UI::Input::KeyboardAndMouse::VK_C;
+
+        eprintln!("⌨️  Simulating Ctrl+C...");
+        send_ctrl_key(VK_C);
+        Ok(())
+    }
+
+    fn synthesize_paste(&self) -> Result<(), Box<dyn Error>> {
+        simulate_paste()
+    }
+
+    fn focused_window(&self) -> Option<isize> {
+        active_window()
+    }
+
+    fn focus_window(&self, hwnd: isize) -> Result<(), Box<dyn Error>> {
+        activate_window(hwnd)
+    }
+
+    fn app_name(&self, hwnd: isize) -> Option<String> {
+        app_name(hwnd).unwrap_or_else(|e| {
+            eprintln!("⚠️  Failed to read the window's process name: {}", e);
+            None
+        })
+    }
+}
+
+// UTF-16 text. Windows synthesizes CF_TEXT and CF_OEMTEXT from it on demand,
+// so reading and writing this one format covers every text consumer.
+const CF_UNICODETEXT: u32 = 13;
+
+// The clipboard is a single global lock; the app that just copied may still hold it
+fn open_clipboard() -> Result<(), Box<dyn Error>> {
+    use windows::Win32::System::DataExchange::OpenClipboard;
+
+    let mut attempts = 0;
+    loop {
+        match unsafe { OpenClipboard(None) } {
+            Ok(()) => return Ok(()),
+            Err(_) if attempts < 10 => {
+                attempts += 1;
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            Err(e) => return Err(format!("Failed to open clipboard: {}", e).into()),
+        }
+    }
+}
+
+fn read_clipboard_text() -> Result<Option<String>, Box<dyn Error>> {
+    use windows::Win32::Foundation::HGLOBAL;
+    use windows::Win32::System::DataExchange::{CloseClipboard, GetClipboardData};
+    use windows::Win32::System::Memory::{GlobalLock, GlobalSize, GlobalUnlock};
+
+    open_clipboard()?;
+
+    unsafe {
+        let mut text = None;
+        if let Ok(h_data) = GetClipboardData(CF_UNICODETEXT) {
+            let h_mem = HGLOBAL(h_data.0 as *mut _);
+            let p_data = GlobalLock(h_mem) as *const u16;
+            if !p_data.is_null() {
+                // Stop at the terminator, never past the allocation
+                let units = std::slice::from_raw_parts(p_data, GlobalSize(h_mem) / 2);
+                let len = units.iter().position(|&unit| unit == 0).unwrap_or(units.len());
+                text = Some(String::from_utf16_lossy(&units[..len]));
+                GlobalUnlock(h_mem).ok();
+            }
+        }
+
+        CloseClipboard().ok();
+        Ok(text)
+    }
+}
+
+fn write_clipboard_text(text: &str) -> Result<(), Box<dyn Error>> {
+    write_clipboard(&ClipboardSnapshot {
+        items: vec![vec![(CF_UNICODETEXT, utf16_bytes(text))]],
+    })
+}
+
+// Null-terminated UTF-16 LE, as CF_UNICODETEXT stores it
+fn utf16_bytes(text: &str) -> Vec<u8> {
+    text.encode_utf16()
+        .chain(std::iter::once(0))
+        .flat_map(u16::to_le_bytes)
+        .collect()
+}
+
+// "HTML Format" is registered at runtime, so its id differs between sessions
+fn html_format() -> Result<u32, Box<dyn Error>> {
+    use windows::core::w;
+    use windows::Win32::System::DataExchange::RegisterClipboardFormatW;
+
+    match unsafe { RegisterClipboardFormatW(w!("HTML Format")) } {
+        0 => Err("Failed to register the HTML clipboard format".into()),
+        format => Ok(format),
+    }
+}
+
+fn read_clipboard_html() -> Result<Option<String>, Box<dyn Error>> {
+    use windows::Win32::Foundation::HGLOBAL;
+    use windows::Win32::System::DataExchange::{CloseClipboard, GetClipboardData};
+    use windows::Win32::System::Memory::{GlobalLock, GlobalSize, GlobalUnlock};
+
+    let format = html_format()?;
+    open_clipboard()?;
+
+    unsafe {
+        let mut html = None;
+        if let Ok(h_data) = GetClipboardData(format) {
+            let h_mem = HGLOBAL(h_data.0 as *mut _);
+            let p_data = GlobalLock(h_mem) as *const u8;
+            if !p_data.is_null() {
+                let bytes = std::slice::from_raw_parts(p_data, GlobalSize(h_mem));
+                let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+                html = cf_html_fragment(&bytes[..len]);
+                GlobalUnlock(h_mem).ok();
+            }
+        }
+
+        CloseClipboard().ok();
+        Ok(html)
+    }
+}
+
+// CF_HTML is UTF-8 behind a header of byte offsets into the whole payload:
+// https://learn.microsoft.com/windows/win32/dataxchg/html-clipboard-format
+fn cf_html_fragment(bytes: &[u8]) -> Option<String> {
+    let offset = |name: &str| -> Option<usize> {
+        let header = std::str::from_utf8(&bytes[..bytes.len().min(512)]).ok()?;
+        let line = header.lines().find_map(|line| line.strip_prefix(name))?;
+        line.trim().parse().ok()
+    };
+
+    let (start, end) = match (offset("StartFragment:"), offset("EndFragment:")) {
+        (Some(start), Some(end)) if start <= end && end <= bytes.len() => (start, end),
+        _ => (offset("StartHTML:")?, offset("EndHTML:").unwrap_or(bytes.len()).min(bytes.len())),
+    };
+    let fragment = bytes.get(start..end)?;
+    Some(String::from_utf8_lossy(fragment).into_owned())
+}
+
+fn cf_html(fragment: &str) -> String {
+    const HEADER: &str = "Version:0.9\r\nStartHTML:0000000000\r\nEndHTML:0000000000\r\n\
+                          StartFragment:0000000000\r\nEndFragment:0000000000\r\n";
+    const PREFIX: &str = "<html><body>\r\n<!--StartFragment-->";
+    const SUFFIX: &str = "<!--EndFragment-->\r\n</body></html>";
+
+    let start_html = HEADER.len();
+    let start_fragment = start_html + PREFIX.len();
+    let end_fragment = start_fragment + fragment.len();
+    let end_html = end_fragment + SUFFIX.len();
+
+    format!(
+        "Version:0.9\r\nStartHTML:{:010}\r\nEndHTML:{:010}\r\nStartFragment:{:010}\r\nEndFragment:{:010}\r\n{}{}{}",
+        start_html, end_html, start_fragment, end_fragment, PREFIX, fragment, SUFFIX
+    )
+}
+
+// Formats whose handle is a GDI object or private pointer rather than global
+// memory: CF_BITMAP, CF_METAFILEPICT, CF_PALETTE, CF_ENHMETAFILE, the CF_DSP*
+// variants, and the private and GDI object ranges. Windows re-synthesizes the
+// bitmap ones from CF_DIB.
+fn is_global_memory_format(format: u32) -> bool {
+    !matches!(format, 2 | 3 | 9 | 14 | 0x80 | 0x82 | 0x83 | 0x8E | 0x200..=0x3FF)
+}
+
+fn snapshot_clipboard() -> Result<ClipboardSnapshot, Box<dyn Error>> {
+    use windows::Win32::Foundation::HGLOBAL;
+    use windows::Win32::System::DataExchange::{
+        CloseClipboard, EnumClipboardFormats, GetClipboardData,
+    };
+    use windows::Win32::System::Memory::{GlobalLock, GlobalSize, GlobalUnlock};
+
+    open_clipboard()?;
+
+    unsafe {
+        let mut flavors = Vec::new();
+        let mut format = EnumClipboardFormats(0);
+        while format != 0 {
+            if is_global_memory_format(format) {
+                if let Ok(handle) = GetClipboardData(format) {
+                    let h_mem = HGLOBAL(handle.0 as *mut _);
+                    let p_data = GlobalLock(h_mem) as *const u8;
+                    if !p_data.is_null() {
+                        let size = GlobalSize(h_mem);
+                        flavors.push((format, std::slice::from_raw_parts(p_data, size).to_vec()));
+                        GlobalUnlock(h_mem).ok();
+                    }
+                }
+            }
+            format = EnumClipboardFormats(format);
+        }
+
+        CloseClipboard().ok();
+        Ok(ClipboardSnapshot { items: vec![flavors] })
+    }
+}
+
+fn write_clipboard(snapshot: &ClipboardSnapshot) -> Result<(), Box<dyn Error>> {
+    use windows::Win32::Foundation::{GlobalFree, HANDLE};
+    use windows::Win32::System::DataExchange::{CloseClipboard, EmptyClipboard, SetClipboardData};
+    use windows::Win32::System::Memory::{GlobalAlloc, GlobalLock, GlobalUnlock, GMEM_MOVEABLE};
+
+    open_clipboard()?;
+
+    unsafe {
+        let result = (|| -> Result<(), Box<dyn Error>> {
+            EmptyClipboard()?;
+            for (format, bytes) in snapshot.items.iter().flatten() {
+                let h_mem = GlobalAlloc(GMEM_MOVEABLE, bytes.len().max(1))?;
+                let p_mem = GlobalLock(h_mem) as *mut u8;
+                if p_mem.is_null() {
+                    GlobalFree(h_mem).ok();
+                    return Err("Failed to lock clipboard memory".into());
+                }
+                std::ptr::copy_nonoverlapping(bytes.as_ptr(), p_mem, bytes.len());
+                GlobalUnlock(h_mem).ok();
+
+                // On success the clipboard owns the memory
+                if SetClipboardData(*format, HANDLE(h_mem.0 as isize)).is_err() {
+                    GlobalFree(h_mem).ok();
+                    eprintln!("⚠️  Failed to restore clipboard format {}", format);
+                }
+            }
+            Ok(())
+        })();
+
+        CloseClipboard().ok();
+        result
+    }
+}
+
+fn clipboard_change_count() -> Result<u64, Box<dyn Error>> {
+    use windows::Win32::System::DataExchange::GetClipboardSequenceNumber;
+
+    Ok(u64::from(unsafe { GetClipboardSequenceNumber() }))
+}
+
+fn active_window() -> Option<isize> {
+    use windows::Win32::UI::WindowsAndMessaging::GetForegroundWindow;
+
+    let hwnd = unsafe { GetForegroundWindow() };
+    (hwnd.0 != 0).then_some(hwnd.0)
+}
+
+// Executable name without extension, e.g. "OUTLOOK" or "slack"
+fn app_name(hwnd: isize) -> Result<Option<String>, Box<dyn Error>> {
+    use windows::core::PWSTR;
+    use windows::Win32::Foundation::{CloseHandle, HWND};
+    use windows::Win32::System::Threading::{
+        OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::GetWindowThreadProcessId;
+
+    let mut pid = 0u32;
+    unsafe { GetWindowThreadProcessId(HWND(hwnd), Some(&mut pid)) };
+    if pid == 0 {
+        return Ok(None);
+    }
+
+    let process = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) }?;
+    let mut buffer = [0u16; 1024];
+    let mut len = buffer.len() as u32;
+    let queried = unsafe {
+        QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, PWSTR(buffer.as_mut_ptr()), &mut len)
+    };
+    let _ = unsafe { CloseHandle(process) };
+    queried?;
+
+    let path = String::from_utf16_lossy(&buffer[..len as usize]);
+    Ok(std::path::Path::new(&path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned()))
+}
+
+fn activate_window(hwnd: isize) -> Result<(), Box<dyn Error>> {
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::UI::WindowsAndMessaging::SetForegroundWindow;
+
+    if !unsafe { SetForegroundWindow(HWND(hwnd)) }.as_bool() {
+        return Err("Could not switch back to the window the text was selected in.".into());
+    }
+    Ok(())
+}
+
+fn simulate_paste() -> Result<(), Box<dyn Error>> {
+    use windows::Win32::UI::Input::KeyboardAndMouse::VK_V;
+
+    send_ctrl_key(VK_V);
+    Ok(())
+}
+
+fn send_ctrl_key(key: windows::Win32::UI::Input::KeyboardAndMouse::VIRTUAL_KEY) {
+    use windows::Win32::UI::Input::KeyboardAndMouse::{
+        SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT, KEYBD_EVENT_FLAGS, KEYEVENTF_KEYUP,
+        VIRTUAL_KEY, VK_CONTROL,
+    };
+
+    let input = |vk: VIRTUAL_KEY, flags: KEYBD_EVENT_FLAGS| INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT {
+                wVk: vk,
+                wScan: 0,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: 0,
+            },
+        },
+    };
+
+    let inputs = [
+        input(VK_CONTROL, KEYBD_EVENT_FLAGS(0)),
+        input(key, KEYBD_EVENT_FLAGS(0)),
+        input(key, KEYEVENTF_KEYUP),
+        input(VK_CONTROL, KEYEVENTF_KEYUP),
+    ];
+
+    unsafe {
+        SendInput(&inputs, std::mem::size_of::<INPUT>() as i32);
+    }
+}
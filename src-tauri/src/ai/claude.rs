@@ -0,0 +1,160 @@
+// Anthropic Messages API backend
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+
+use super::sse::read_stream;
+use super::vendor::{redact, VendorError};
+use super::{build_prompt, clean_response, send, ChunkSink, Provider, ProviderCapabilities, RephraseError, RephraseRequest};
+
+const MODEL: &str = "claude-sonnet-4-6";
+
+pub struct ClaudeProvider;
+
+impl ClaudeProvider {
+    fn build_request(&self, client: &Client, request: &RephraseRequest<'_>, stream: bool) -> RequestBuilder {
+        let model = request.model(MODEL);
+        let prompt = build_prompt(request);
+        let body = ClaudeRequest {
+            model: model.to_string(),
+            max_tokens: request.settings.max_output_tokens.unwrap_or(2048),
+            temperature: request.settings.temperature,
+            top_p: request.settings.top_p,
+            system: prompt.system,
+            messages: prompt
+                .turns
+                .into_iter()
+                .map(|(role, content)| ClaudeMessage {
+                    role: role.as_str().to_string(),
+                    content,
+                })
+                .collect(),
+            stream,
+        };
+
+        eprintln!("📤 Sending request to Claude API: model={}, stream={}, text_len={}", model, stream, request.text.len());
+
+        client
+            .post("https://api.anthropic.com/v1/messages")
+            .header("x-api-key", request.api_key)
+            .header("anthropic-version", "2023-06-01")
+            .header("Content-Type", "application/json")
+            .json(&body)
+            .timeout(request.timeout(if stream { 60 } else { 30 }))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ClaudeMessage {
+    role: String,
+    content: String,
+}
+
+#[derive(Serialize)]
+struct ClaudeRequest {
+    model: String,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    system: String,
+    messages: Vec<ClaudeMessage>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+}
+
+#[derive(Deserialize)]
+struct ClaudeResponse {
+    content: Vec<ClaudeContent>,
+}
+
+#[derive(Deserialize)]
+struct ClaudeContent {
+    text: String,
+}
+
+// Only the stream events that carry text or errors are of interest;
+// message_start, ping, content_block_stop etc. fall through to `Other`.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClaudeStreamEvent {
+    ContentBlockDelta { delta: ClaudeDelta },
+    Error { error: ClaudeStreamError },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct ClaudeDelta {
+    #[serde(default)]
+    text: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ClaudeStreamError {
+    #[serde(default, rename = "type")]
+    kind: Option<String>,
+    message: String,
+}
+
+#[async_trait]
+impl Provider for ClaudeProvider {
+    fn id(&self) -> &str {
+        "claude"
+    }
+
+    fn display_name(&self) -> &str {
+        "Claude"
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: true,
+            system_prompt: true,
+            max_input_chars: 10000,
+            models: vec![MODEL.to_string()],
+        }
+    }
+
+    async fn rephrase(&self, client: &Client, request: &RephraseRequest<'_>) -> Result<String, RephraseError> {
+        eprintln!("🤖 Using Anthropic Claude for rephrasing");
+
+        let response = send(self.build_request(client, request, false), self.display_name()).await?;
+
+        let data: ClaudeResponse = response.json().await?;
+        let rephrased = data.content.first()
+            .map(|c| c.text.trim().to_string())
+            .ok_or_else(|| RephraseError::EmptyResponse { provider: self.display_name().to_string() })?;
+
+        Ok(clean_response(self.display_name(), &rephrased))
+    }
+
+    async fn rephrase_stream(
+        &self,
+        client: &Client,
+        request: &RephraseRequest<'_>,
+        on_chunk: &ChunkSink<'_>,
+    ) -> Result<String, RephraseError> {
+        eprintln!("🤖 Streaming from Anthropic Claude");
+
+        let response = send(self.build_request(client, request, true), self.display_name()).await?;
+
+        let rephrased = read_stream(response, self.display_name(), on_chunk, |payload| {
+            match serde_json::from_str::<ClaudeStreamEvent>(payload)? {
+                ClaudeStreamEvent::ContentBlockDelta { delta } => Ok(delta.text),
+                ClaudeStreamEvent::Error { error } => Err(RephraseError::ProviderMessage {
+                    provider: "Claude".to_string(),
+                    body: VendorError { kind: error.kind, message: redact(&error.message) }.to_string(),
+                }),
+                ClaudeStreamEvent::Other => Ok(None),
+            }
+        }).await?;
+
+        if rephrased.trim().is_empty() {
+            return Err(RephraseError::EmptyResponse { provider: self.display_name().to_string() });
+        }
+
+        Ok(clean_response(self.display_name(), rephrased.trim()))
+    }
+}
@@ -0,0 +1,341 @@
+// Structured errors for the AI layer, serialized to the frontend as tagged JSON
+use serde::ser::{SerializeMap, Serializer};
+use serde::Serialize;
+use std::time::Duration;
+
+use super::vendor::{redact, VendorError};
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum RephraseError {
+    #[error("Request timed out. The AI service may be slow — please try again.")]
+    Timeout,
+
+    #[error(
+        "Cannot connect to API server. This may be caused by a network issue \
+         or the app's security policy blocking the request. \
+         Please check your internet connection. (Details: {details})"
+    )]
+    Connect { details: String },
+
+    #[error(
+        "Failed to send request. The API domain may not be allowed by the app's \
+         security policy. Please update the app or contact support. (Details: {details})"
+    )]
+    Request { details: String },
+
+    #[error("Network error: {details}")]
+    Network { details: String },
+
+    #[error("{provider}: Invalid API key. Please verify your key in Settings and ensure it has not expired.{}", details(.vendor))]
+    Auth { provider: String, vendor: Option<VendorError> },
+
+    #[error(
+        "{provider}: Access denied. Your API key may lack the required permissions, \
+         or the model may not be available on your plan.{}",
+        details(.vendor)
+    )]
+    Forbidden { provider: String, vendor: Option<VendorError> },
+
+    #[error(
+        "{provider}: Rate limit exceeded. Please wait a moment and try again, \
+         or check your API plan's usage limits.{}",
+        details(.vendor)
+    )]
+    RateLimited { provider: String, retry_after: Option<Duration>, vendor: Option<VendorError> },
+
+    #[error("{provider}: Usage quota exceeded. Please check your plan and billing details.{}", details(.vendor))]
+    QuotaExceeded { provider: String, vendor: Option<VendorError> },
+
+    #[error(
+        "{provider}: Model or endpoint not found. The model may have been deprecated or renamed — \
+         choose a different model for this provider in Settings.{}",
+        details(.vendor)
+    )]
+    ModelNotFound { provider: String, vendor: Option<VendorError> },
+
+    // Without a vendor message the most common cause is oversized input
+    #[error("{provider}: {}", match .vendor {
+        Some(vendor) => format!("Request rejected — {}", vendor),
+        None => "Bad request. The text may be too long or contain unsupported characters.".to_string(),
+    })]
+    BadRequest { provider: String, vendor: Option<VendorError> },
+
+    #[error("{provider}: Service temporarily unavailable (HTTP {status}). Please try again in a few seconds.{}", details(.vendor))]
+    Unavailable { provider: String, status: u16, retry_after: Option<Duration>, vendor: Option<VendorError> },
+
+    #[error("{provider}: Unexpected error (HTTP {status}). Please try again or check your API key.{}", details(.vendor))]
+    Http { provider: String, status: u16, vendor: Option<VendorError> },
+
+    #[error("{provider}: {body}")]
+    ProviderMessage { provider: String, body: String },
+
+    #[error("No response from {provider}")]
+    EmptyResponse { provider: String },
+
+    /// The reply answers or obeys the selected text instead of rewriting it,
+    /// usually because the text reads like a question or an instruction.
+    #[error("{provider} responded to the text instead of rephrasing it. Please try again or pick another provider.")]
+    OffTask { provider: String },
+
+    #[error("Could not read the AI service response: {details}")]
+    InvalidResponse { details: String },
+
+    #[error("Text too long. Maximum {max_chars} characters allowed.")]
+    InputTooLong { max_chars: usize },
+
+    #[error("Text cannot be empty")]
+    EmptyInput,
+
+    #[error("API key is required for custom providers. Please configure it in Settings or use the default (Proxy Server).")]
+    MissingApiKey { provider: String },
+
+    #[error("Unsupported provider: {provider}")]
+    UnsupportedProvider { provider: String },
+
+    #[error("Unknown style: {style}. It may have been deleted — pick another style.")]
+    UnknownStyle { style: String },
+
+    #[error("Request cancelled")]
+    Cancelled,
+}
+
+impl RephraseError {
+    /// Maps a transport-level failure (no HTTP status available). The URL is
+    /// dropped from the details, since some endpoints take credentials in it.
+    pub fn from_request(e: reqwest::Error) -> Self {
+        let e = e.without_url();
+        let details = || redact(&e.to_string());
+        if e.is_timeout() {
+            Self::Timeout
+        } else if e.is_connect() {
+            Self::Connect { details: details() }
+        } else if e.is_request() {
+            Self::Request { details: details() }
+        } else if e.is_decode() {
+            Self::InvalidResponse { details: details() }
+        } else {
+            Self::Network { details: details() }
+        }
+    }
+
+    /// Maps a non-2xx HTTP status, refined by the vendor's error type where
+    /// the status alone is ambiguous.
+    pub fn from_status(status: u16, provider: &str, retry_after: Option<Duration>, vendor: Option<VendorError>) -> Self {
+        let provider = provider.to_string();
+        let vendor_kind = vendor.as_ref().and_then(|v| v.kind.as_deref()).unwrap_or_default();
+        match (status, vendor_kind) {
+            // OpenAI reports an exhausted balance as 429, which must not be retried
+            (_, "insufficient_quota" | "billing_hard_limit_reached") => Self::QuotaExceeded { provider, vendor },
+            (_, "model_not_found") => Self::ModelNotFound { provider, vendor },
+            (400, _) => Self::BadRequest { provider, vendor },
+            (401, _) => Self::Auth { provider, vendor },
+            (402, _) => Self::QuotaExceeded { provider, vendor },
+            (403, _) => Self::Forbidden { provider, vendor },
+            (404, _) => Self::ModelNotFound { provider, vendor },
+            (429, _) => Self::RateLimited { provider, retry_after, vendor },
+            (500..=599, _) => Self::Unavailable { provider, status, retry_after, vendor },
+            _ => Self::Http { provider, status, vendor },
+        }
+    }
+
+    /// Transient failures worth another attempt: timeouts, connection resets,
+    /// rate limits and 5xx/529 overloads.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::Timeout | Self::Connect { .. } | Self::RateLimited { .. } | Self::Unavailable { .. }
+        )
+    }
+
+    /// Wait time requested by the server, if it sent one.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::RateLimited { retry_after, .. } | Self::Unavailable { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// Stable tag the frontend switches on.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Timeout => "timeout",
+            Self::Connect { .. } => "connect",
+            Self::Request { .. } => "request",
+            Self::Network { .. } => "network",
+            Self::Auth { .. } => "auth",
+            Self::Forbidden { .. } => "forbidden",
+            Self::RateLimited { .. } => "rate_limited",
+            Self::QuotaExceeded { .. } => "quota_exceeded",
+            Self::ModelNotFound { .. } => "model_not_found",
+            Self::BadRequest { .. } => "bad_request",
+            Self::Unavailable { .. } => "unavailable",
+            Self::Http { .. } => "http",
+            Self::ProviderMessage { .. } => "provider_message",
+            Self::EmptyResponse { .. } => "empty_response",
+            Self::OffTask { .. } => "off_task",
+            Self::InvalidResponse { .. } => "invalid_response",
+            Self::InputTooLong { .. } => "input_too_long",
+            Self::EmptyInput => "empty_input",
+            Self::MissingApiKey { .. } => "missing_api_key",
+            Self::UnsupportedProvider { .. } => "unsupported_provider",
+            Self::UnknownStyle { .. } => "unknown_style",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    /// Error reported in the provider's response body, if any.
+    pub fn vendor(&self) -> Option<&VendorError> {
+        match self {
+            Self::Auth { vendor, .. }
+            | Self::Forbidden { vendor, .. }
+            | Self::RateLimited { vendor, .. }
+            | Self::QuotaExceeded { vendor, .. }
+            | Self::ModelNotFound { vendor, .. }
+            | Self::BadRequest { vendor, .. }
+            | Self::Unavailable { vendor, .. }
+            | Self::Http { vendor, .. } => vendor.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub fn provider(&self) -> Option<&str> {
+        match self {
+            Self::Auth { provider, .. }
+            | Self::Forbidden { provider, .. }
+            | Self::RateLimited { provider, .. }
+            | Self::QuotaExceeded { provider, .. }
+            | Self::ModelNotFound { provider, .. }
+            | Self::BadRequest { provider, .. }
+            | Self::Unavailable { provider, .. }
+            | Self::Http { provider, .. }
+            | Self::ProviderMessage { provider, .. }
+            | Self::EmptyResponse { provider }
+            | Self::OffTask { provider }
+            | Self::MissingApiKey { provider }
+            | Self::UnsupportedProvider { provider } => Some(provider),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for RephraseError {
+    fn from(e: reqwest::Error) -> Self {
+        Self::from_request(e)
+    }
+}
+
+impl From<serde_json::Error> for RephraseError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::InvalidResponse { details: e.to_string() }
+    }
+}
+
+// Serialized as `{ "kind": "...", "message": "...", ...variant fields }` so the
+// UI can branch on `kind` and still show `message` verbatim.
+impl Serialize for RephraseError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("kind", self.kind())?;
+        map.serialize_entry("message", &self.to_string())?;
+        if let Some(provider) = self.provider() {
+            map.serialize_entry("provider", provider)?;
+        }
+        if let Some(retry_after) = self.retry_after() {
+            map.serialize_entry("retry_after", &retry_after.as_secs_f64())?;
+        }
+        if let Some(vendor) = self.vendor() {
+            map.serialize_entry("vendor_type", &vendor.kind)?;
+            map.serialize_entry("vendor_message", &vendor.message)?;
+        }
+        match self {
+            Self::Unavailable { status, .. } | Self::Http { status, .. } => map.serialize_entry("status", status)?,
+            Self::ProviderMessage { body, .. } => map.serialize_entry("body", body)?,
+            Self::InputTooLong { max_chars } => map.serialize_entry("max_chars", max_chars)?,
+            Self::UnknownStyle { style } => map.serialize_entry("style", style)?,
+            _ => {}
+        }
+        map.end()
+    }
+}
+
+// Appended to the fixed explanation when the provider said more
+fn details(vendor: &Option<VendorError>) -> String {
+    match vendor {
+        Some(vendor) => format!(" (Details: {})", vendor),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vendor(kind: &str) -> Option<VendorError> {
+        Some(VendorError { kind: Some(kind.to_string()), message: "details".to_string() })
+    }
+
+    #[test]
+    fn maps_statuses_to_variants() {
+        let kind = |status| RephraseError::from_status(status, "OpenAI", None, None).kind();
+        assert_eq!(kind(400), "bad_request");
+        assert_eq!(kind(401), "auth");
+        assert_eq!(kind(402), "quota_exceeded");
+        assert_eq!(kind(403), "forbidden");
+        assert_eq!(kind(404), "model_not_found");
+        assert_eq!(kind(429), "rate_limited");
+        assert_eq!(kind(500), "unavailable");
+        assert_eq!(kind(529), "unavailable");
+        assert_eq!(kind(418), "http");
+    }
+
+    #[test]
+    fn vendor_type_refines_the_status() {
+        let quota = RephraseError::from_status(429, "OpenAI", Some(Duration::from_secs(1)), vendor("insufficient_quota"));
+        assert_eq!(quota.kind(), "quota_exceeded");
+        assert!(!quota.is_retryable());
+
+        let model = RephraseError::from_status(400, "OpenAI", None, vendor("model_not_found"));
+        assert_eq!(model.kind(), "model_not_found");
+
+        let limited = RephraseError::from_status(429, "OpenAI", None, vendor("requests"));
+        assert_eq!(limited.kind(), "rate_limited");
+    }
+
+    #[test]
+    fn only_transient_errors_are_retryable() {
+        let wait = Some(Duration::from_secs(2));
+        let limited = RephraseError::from_status(429, "Claude", wait, None);
+        assert!(limited.is_retryable());
+        assert_eq!(limited.retry_after(), wait);
+
+        let overloaded = RephraseError::from_status(529, "Claude", wait, None);
+        assert!(overloaded.is_retryable());
+        assert_eq!(overloaded.retry_after(), wait);
+
+        assert!(RephraseError::Timeout.is_retryable());
+        assert!(RephraseError::Connect { details: String::new() }.is_retryable());
+
+        for status in [400, 401, 402, 403, 404, 418] {
+            let error = RephraseError::from_status(status, "Claude", wait, None);
+            assert!(!error.is_retryable(), "{}", status);
+            assert_eq!(error.retry_after(), None, "{}", status);
+        }
+        assert!(!RephraseError::OffTask { provider: "Claude".to_string() }.is_retryable());
+        assert!(!RephraseError::Cancelled.is_retryable());
+    }
+
+    #[test]
+    fn serializes_kind_message_and_vendor() {
+        let error = RephraseError::from_status(401, "Gemini", None, vendor("UNAUTHENTICATED"));
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(json["kind"], "auth");
+        assert_eq!(json["provider"], "Gemini");
+        assert_eq!(json["vendor_type"], "UNAUTHENTICATED");
+        assert_eq!(json["vendor_message"], "details");
+        assert!(json["message"].as_str().unwrap().ends_with("(Details: UNAUTHENTICATED: details)"));
+
+        let json = serde_json::to_value(RephraseError::InputTooLong { max_chars: 10000 }).unwrap();
+        assert_eq!(json["kind"], "input_too_long");
+        assert_eq!(json["max_chars"], 10000);
+    }
+}
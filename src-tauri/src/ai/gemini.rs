@@ -0,0 +1,187 @@
+// Google Gemini generateContent backend
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+
+use super::sse::read_stream;
+use super::{build_prompt, clean_response, send, ChunkSink, Provider, ProviderCapabilities, RephraseError, RephraseRequest, Role};
+
+const MODEL: &str = "gemini-2.5-flash";
+
+pub struct GeminiProvider;
+
+impl GeminiProvider {
+    fn build_request(&self, client: &Client, request: &RephraseRequest<'_>, stream: bool) -> RequestBuilder {
+        let prompt = build_prompt(request);
+        let body = GeminiRequest {
+            system_instruction: GeminiContent {
+                role: String::new(),
+                parts: vec![GeminiPart { text: prompt.system }],
+            },
+            contents: prompt
+                .turns
+                .into_iter()
+                .map(|(role, text)| GeminiContent {
+                    // Gemini calls the assistant side "model"
+                    role: match role {
+                        Role::User => "user",
+                        Role::Assistant => "model",
+                    }
+                    .to_string(),
+                    parts: vec![GeminiPart { text }],
+                })
+                .collect(),
+            generation_config: GeminiGenerationConfig {
+                temperature: request.settings.temperature,
+                max_output_tokens: request.settings.max_output_tokens,
+                top_p: request.settings.top_p,
+            },
+        };
+
+        let model = request.model(MODEL);
+        let url = if stream {
+            format!(
+                "https://generativelanguage.googleapis.com/v1/models/{}:streamGenerateContent?alt=sse",
+                model
+            )
+        } else {
+            format!(
+                "https://generativelanguage.googleapis.com/v1/models/{}:generateContent",
+                model
+            )
+        };
+
+        eprintln!("📤 Sending request to Gemini: model={}, stream={}, text_len={}", model, stream, request.text.len());
+
+        // In a header rather than the `key` query parameter, which would end up
+        // in transport errors and logs along with the URL
+        client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .header("x-goog-api-key", request.api_key)
+            .json(&body)
+            .timeout(request.timeout(if stream { 60 } else { 30 }))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct GeminiPart {
+    text: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GeminiContent {
+    // Left out of the system instruction, which has no role
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    role: String,
+    parts: Vec<GeminiPart>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiRequest {
+    system_instruction: GeminiContent,
+    contents: Vec<GeminiContent>,
+    generation_config: GeminiGenerationConfig,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiGenerationConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct GeminiResponse {
+    candidates: Vec<GeminiCandidate>,
+}
+
+#[derive(Deserialize)]
+struct GeminiCandidate {
+    content: GeminiContent,
+}
+
+// Stream chunks may omit candidates or parts entirely (e.g. the final usage chunk)
+#[derive(Deserialize)]
+struct GeminiStreamChunk {
+    #[serde(default)]
+    candidates: Vec<GeminiStreamCandidate>,
+}
+
+#[derive(Deserialize)]
+struct GeminiStreamCandidate {
+    #[serde(default)]
+    content: Option<GeminiStreamContent>,
+}
+
+#[derive(Deserialize)]
+struct GeminiStreamContent {
+    #[serde(default)]
+    parts: Vec<GeminiPart>,
+}
+
+#[async_trait]
+impl Provider for GeminiProvider {
+    fn id(&self) -> &str {
+        "gemini"
+    }
+
+    fn display_name(&self) -> &str {
+        "Gemini"
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: true,
+            system_prompt: true,
+            max_input_chars: 10000,
+            models: vec![MODEL.to_string()],
+        }
+    }
+
+    async fn rephrase(&self, client: &Client, request: &RephraseRequest<'_>) -> Result<String, RephraseError> {
+        eprintln!("🤖 Using Google Gemini for rephrasing");
+
+        let response = send(self.build_request(client, request, false), self.display_name()).await?;
+
+        let data: GeminiResponse = response.json().await?;
+        let rephrased = data.candidates.first()
+            .and_then(|c| c.content.parts.first())
+            .map(|p| p.text.trim().to_string())
+            .ok_or_else(|| RephraseError::EmptyResponse { provider: self.display_name().to_string() })?;
+
+        Ok(clean_response(self.display_name(), &rephrased))
+    }
+
+    async fn rephrase_stream(
+        &self,
+        client: &Client,
+        request: &RephraseRequest<'_>,
+        on_chunk: &ChunkSink<'_>,
+    ) -> Result<String, RephraseError> {
+        eprintln!("🤖 Streaming from Google Gemini");
+
+        let response = send(self.build_request(client, request, true), self.display_name()).await?;
+
+        let rephrased = read_stream(response, self.display_name(), on_chunk, |payload| {
+            let chunk: GeminiStreamChunk = serde_json::from_str(payload)?;
+            let text: String = chunk.candidates.into_iter()
+                .next()
+                .and_then(|c| c.content)
+                .map(|c| c.parts.into_iter().map(|p| p.text).collect())
+                .unwrap_or_default();
+            Ok(Some(text))
+        }).await?;
+
+        if rephrased.trim().is_empty() {
+            return Err(RephraseError::EmptyResponse { provider: self.display_name().to_string() });
+        }
+
+        Ok(clean_response(self.display_name(), rephrased.trim()))
+    }
+}
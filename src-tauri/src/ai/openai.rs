@@ -0,0 +1,356 @@
+// OpenAI Chat Completions backend, also used for any OpenAI-compatible server
+// (Ollama, llama.cpp server, vLLM, LM Studio, corporate gateways)
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::config::CustomEndpoint;
+
+use super::sse::read_stream;
+use super::{build_prompt, clean_response, send, ChunkSink, Provider, ProviderCapabilities, RephraseError, RephraseRequest};
+
+const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+const OPENAI_MODEL: &str = "gpt-4o-mini";
+const DEFAULT_MAX_INPUT_CHARS: usize = 10000;
+
+pub struct OpenAiProvider {
+    id: String,
+    name: String,
+    base_url: String,
+    model: String,
+    auth_header: String,
+    extra_headers: HashMap<String, String>,
+    requires_api_key: bool,
+    max_input_chars: usize,
+    models: Vec<String>,
+}
+
+impl OpenAiProvider {
+    /// The hosted OpenAI API.
+    pub fn openai() -> Self {
+        Self {
+            id: "openai".to_string(),
+            name: "OpenAI".to_string(),
+            base_url: OPENAI_BASE_URL.to_string(),
+            model: OPENAI_MODEL.to_string(),
+            auth_header: "Authorization".to_string(),
+            extra_headers: HashMap::new(),
+            requires_api_key: true,
+            max_input_chars: DEFAULT_MAX_INPUT_CHARS,
+            models: vec![OPENAI_MODEL.to_string()],
+        }
+    }
+
+    /// A user-configured OpenAI-compatible endpoint. The API key is optional
+    /// because most local servers accept unauthenticated requests.
+    pub fn custom(endpoint: &CustomEndpoint) -> Self {
+        let mut models: Vec<String> = endpoint
+            .models
+            .iter()
+            .map(|model| model.trim().to_string())
+            .filter(|model| !model.is_empty())
+            .collect();
+        if !models.contains(&endpoint.model) {
+            models.insert(0, endpoint.model.clone());
+        }
+
+        Self {
+            id: endpoint.id.clone(),
+            name: endpoint.name.clone(),
+            base_url: endpoint.base_url.trim_end_matches('/').to_string(),
+            model: endpoint.model.clone(),
+            auth_header: endpoint
+                .auth_header
+                .clone()
+                .filter(|h| !h.trim().is_empty())
+                .unwrap_or_else(|| "Authorization".to_string()),
+            extra_headers: endpoint.extra_headers.clone(),
+            requires_api_key: false,
+            max_input_chars: endpoint.max_input_chars.unwrap_or(DEFAULT_MAX_INPUT_CHARS),
+            models,
+        }
+    }
+
+    fn auth_value(&self, api_key: &str) -> String {
+        if self.auth_header.eq_ignore_ascii_case("Authorization") {
+            format!("Bearer {}", api_key)
+        } else {
+            api_key.to_string()
+        }
+    }
+
+    fn build_request(&self, client: &Client, request: &RephraseRequest<'_>, stream: bool) -> RequestBuilder {
+        let model = request.model(&self.model);
+        let prompt = build_prompt(request);
+        let mut messages = vec![
+            OpenAIMessage {
+                role: "system".to_string(),
+                content: prompt.system,
+            },
+        ];
+        messages.extend(prompt.turns.into_iter().map(|(role, content)| OpenAIMessage {
+            role: role.as_str().to_string(),
+            content,
+        }));
+        let body = OpenAIRequest {
+            model: model.to_string(),
+            messages,
+            temperature: request.settings.temperature.unwrap_or(0.7),
+            max_tokens: request.settings.max_output_tokens,
+            top_p: request.settings.top_p,
+            stream,
+        };
+
+        let url = format!("{}/chat/completions", self.base_url);
+        eprintln!("📤 Sending request to {}: url={}, model={}, stream={}, text_len={}", self.name, url, model, stream, request.text.len());
+
+        let mut http_request = client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .json(&body)
+            .timeout(request.timeout(if stream { 60 } else { 30 }));
+
+        if !request.api_key.trim().is_empty() {
+            http_request = http_request.header(self.auth_header.as_str(), self.auth_value(request.api_key));
+        }
+        for (name, value) in &self.extra_headers {
+            http_request = http_request.header(name.as_str(), value.as_str());
+        }
+
+        http_request
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct OpenAIMessage {
+    role: String,
+    content: String,
+}
+
+#[derive(Serialize)]
+struct OpenAIRequest {
+    model: String,
+    messages: Vec<OpenAIMessage>,
+    temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+}
+
+#[derive(Deserialize)]
+struct OpenAIResponse {
+    choices: Vec<OpenAIChoice>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIChoice {
+    message: OpenAIMessage,
+}
+
+#[derive(Deserialize)]
+struct OpenAIStreamChunk {
+    choices: Vec<OpenAIStreamChoice>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIStreamChoice {
+    delta: OpenAIDelta,
+}
+
+#[derive(Deserialize)]
+struct OpenAIDelta {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+#[async_trait]
+impl Provider for OpenAiProvider {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn display_name(&self) -> &str {
+        &self.name
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: true,
+            system_prompt: true,
+            max_input_chars: self.max_input_chars,
+            models: self.models.clone(),
+        }
+    }
+
+    fn requires_api_key(&self) -> bool {
+        self.requires_api_key
+    }
+
+    async fn rephrase(&self, client: &Client, request: &RephraseRequest<'_>) -> Result<String, RephraseError> {
+        eprintln!("🤖 Using {} for rephrasing", self.name);
+
+        let response = send(self.build_request(client, request, false), &self.name).await?;
+
+        let data: OpenAIResponse = response.json().await?;
+        let rephrased = data.choices.first()
+            .map(|c| c.message.content.trim().to_string())
+            .ok_or_else(|| RephraseError::EmptyResponse { provider: self.name.clone() })?;
+
+        Ok(clean_response(&self.name, &rephrased))
+    }
+
+    async fn rephrase_stream(
+        &self,
+        client: &Client,
+        request: &RephraseRequest<'_>,
+        on_chunk: &ChunkSink<'_>,
+    ) -> Result<String, RephraseError> {
+        eprintln!("🤖 Streaming from {}", self.name);
+
+        let response = send(self.build_request(client, request, true), &self.name).await?;
+
+        let rephrased = read_stream(response, &self.name, on_chunk, |payload| {
+            let chunk: OpenAIStreamChunk = serde_json::from_str(payload)?;
+            Ok(chunk.choices.into_iter().next().and_then(|c| c.delta.content))
+        }).await?;
+
+        if rephrased.trim().is_empty() {
+            return Err(RephraseError::EmptyResponse { provider: self.name.clone() });
+        }
+
+        Ok(clean_response(&self.name, rephrased.trim()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ProviderSettings;
+    use crate::styles::StyleRegistry;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    const REPLY: &str = r#"{"choices":[{"message":{"role":"assistant","content":"Rephrased."}}]}"#;
+
+    // Serves one chat completion and hands back the raw request it received
+    async fn mock_server() -> (String, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut raw = Vec::new();
+            let mut buf = [0u8; 4096];
+            loop {
+                let n = socket.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                raw.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&raw);
+                if let Some(head_end) = text.find("\r\n\r\n") {
+                    let length = text[..head_end]
+                        .lines()
+                        .find_map(|line| line.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().to_string()))
+                        .and_then(|v| v.parse::<usize>().ok())
+                        .unwrap_or(0);
+                    if raw.len() >= head_end + 4 + length {
+                        break;
+                    }
+                }
+            }
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                REPLY.len(),
+                REPLY
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8(raw).unwrap()
+        });
+        (format!("http://{}", addr), handle)
+    }
+
+    fn endpoint(base_url: String) -> CustomEndpoint {
+        CustomEndpoint {
+            id: "local".to_string(),
+            name: "Local".to_string(),
+            base_url,
+            model: "llama3.1".to_string(),
+            auth_header: None,
+            extra_headers: HashMap::new(),
+            max_input_chars: None,
+            models: Vec::new(),
+        }
+    }
+
+    async fn rephrase(provider: &OpenAiProvider, api_key: &str) -> String {
+        let style = StyleRegistry::default().get("professional").unwrap();
+        let request = RephraseRequest {
+            text: "hey can u send the file",
+            style: &style,
+            variables: &HashMap::new(),
+            markdown: false,
+            api_key,
+            settings: ProviderSettings::default(),
+        };
+        provider.rephrase(&Client::new(), &request).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn custom_endpoint_sends_configured_headers() {
+        let (url, server) = mock_server().await;
+        let mut endpoint = endpoint(format!("{}/v1/", url));
+        endpoint.auth_header = Some("api-key".to_string());
+        endpoint.extra_headers.insert("X-Team".to_string(), "writers".to_string());
+        let provider = OpenAiProvider::custom(&endpoint);
+
+        assert_eq!(rephrase(&provider, "secret-123").await, "Rephrased.");
+
+        let raw = server.await.unwrap();
+        let lower = raw.to_ascii_lowercase();
+        assert!(raw.starts_with("POST /v1/chat/completions HTTP/1.1"), "{}", raw);
+        assert!(lower.contains("\r\napi-key: secret-123\r\n"), "{}", raw);
+        assert!(lower.contains("\r\nx-team: writers\r\n"), "{}", raw);
+        assert!(!lower.contains("\r\nauthorization:"), "{}", raw);
+        assert!(raw.contains(r#""model":"llama3.1""#), "{}", raw);
+    }
+
+    #[tokio::test]
+    async fn custom_endpoint_defaults_to_bearer_auth() {
+        let (url, server) = mock_server().await;
+        let provider = OpenAiProvider::custom(&endpoint(format!("{}/v1", url)));
+
+        rephrase(&provider, "secret-123").await;
+
+        let raw = server.await.unwrap().to_ascii_lowercase();
+        assert!(raw.contains("\r\nauthorization: bearer secret-123\r\n"), "{}", raw);
+    }
+
+    #[tokio::test]
+    async fn custom_endpoint_without_a_key_sends_no_auth() {
+        let (url, server) = mock_server().await;
+        let provider = OpenAiProvider::custom(&endpoint(format!("{}/v1", url)));
+        assert!(!provider.requires_api_key());
+
+        rephrase(&provider, "").await;
+
+        let raw = server.await.unwrap().to_ascii_lowercase();
+        assert!(!raw.contains("\r\nauthorization:"), "{}", raw);
+    }
+
+    #[test]
+    fn custom_endpoint_capabilities_come_from_config() {
+        let mut endpoint = endpoint("http://localhost:11434/v1".to_string());
+        let defaults = OpenAiProvider::custom(&endpoint).capabilities();
+        assert_eq!(defaults.max_input_chars, DEFAULT_MAX_INPUT_CHARS);
+        assert_eq!(defaults.models, vec!["llama3.1"]);
+
+        endpoint.max_input_chars = Some(32000);
+        endpoint.models = vec!["qwen2.5".to_string(), " ".to_string()];
+        let configured = OpenAiProvider::custom(&endpoint).capabilities();
+        assert_eq!(configured.max_input_chars, 32000);
+        assert_eq!(configured.models, vec!["llama3.1", "qwen2.5"]);
+    }
+}
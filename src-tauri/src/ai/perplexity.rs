@@ -0,0 +1,102 @@
+// Perplexity Sonar backend
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use super::{build_prompt, clean_response, send, Provider, ProviderCapabilities, RephraseError, RephraseRequest};
+
+const MODEL: &str = "sonar";
+
+pub struct PerplexityProvider;
+
+#[derive(Serialize, Deserialize)]
+struct PerplexityMessage {
+    role: String,
+    content: String,
+}
+
+#[derive(Serialize)]
+struct PerplexityRequest {
+    model: String,
+    messages: Vec<PerplexityMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct PerplexityResponse {
+    choices: Vec<PerplexityChoice>,
+}
+
+#[derive(Deserialize)]
+struct PerplexityChoice {
+    message: PerplexityMessage,
+}
+
+#[async_trait]
+impl Provider for PerplexityProvider {
+    fn id(&self) -> &str {
+        "perplexity"
+    }
+
+    fn display_name(&self) -> &str {
+        "Perplexity"
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: false,
+            system_prompt: true,
+            max_input_chars: 10000,
+            models: vec![MODEL.to_string()],
+        }
+    }
+
+    async fn rephrase(&self, client: &Client, request: &RephraseRequest<'_>) -> Result<String, RephraseError> {
+        eprintln!("🤖 Using Perplexity for rephrasing");
+
+        let model = request.model(MODEL);
+        let prompt = build_prompt(request);
+        let mut messages = vec![
+            PerplexityMessage {
+                role: "system".to_string(),
+                content: prompt.system,
+            },
+        ];
+        messages.extend(prompt.turns.into_iter().map(|(role, content)| PerplexityMessage {
+            role: role.as_str().to_string(),
+            content,
+        }));
+        let body = PerplexityRequest {
+            model: model.to_string(),
+            messages,
+            temperature: request.settings.temperature,
+            max_tokens: request.settings.max_output_tokens,
+            top_p: request.settings.top_p,
+        };
+
+        eprintln!("📤 Sending request to Perplexity: model={}, text_len={}", model, request.text.len());
+
+        let response = send(
+            client
+                .post("https://api.perplexity.ai/v1/sonar")
+                .header("Authorization", format!("Bearer {}", request.api_key))
+                .header("Content-Type", "application/json")
+                .json(&body)
+                .timeout(request.timeout(30)),
+            self.display_name(),
+        ).await?;
+
+        let data: PerplexityResponse = response.json().await?;
+        let rephrased = data.choices.first()
+            .map(|c| c.message.content.trim().to_string())
+            .ok_or_else(|| RephraseError::EmptyResponse { provider: self.display_name().to_string() })?;
+
+        // Strip any preamble text that Perplexity might add
+        Ok(clean_response(self.display_name(), &rephrased))
+    }
+}
@@ -0,0 +1,73 @@
+// Pipeline styles: several styles run in order, each on the previous output
+use reqwest::Client;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use super::{rephrase_step, ApiKeyLookup, ChunkSink, ProviderRegistry, RephraseError, RephraseOutcome, Step};
+
+/// What one step of a pipeline produced, kept so the popup can show how the
+/// text got from the selection to the result.
+#[derive(Debug, Clone, Serialize)]
+pub struct StepOutput {
+    pub index: usize,
+    /// Name of the style the step applied.
+    pub style: String,
+    pub provider: String,
+    pub provider_name: String,
+    pub text: String,
+}
+
+/// Receives each step's output as soon as the step finishes.
+pub type StepSink<'a> = dyn Fn(&StepOutput) + Send + Sync + 'a;
+
+/// Runs `steps` in order, feeding each one the previous step's output. Every
+/// step gets the full retry and fallback treatment of a single rephrase, and
+/// the first step that fails ends the pipeline. `steps` must not be empty.
+#[allow(clippy::too_many_arguments)]
+pub async fn rephrase_pipeline(
+    registry: &ProviderRegistry,
+    client: &Client,
+    text: &str,
+    steps: &[Step<'_>],
+    variables: &HashMap<String, String>,
+    api_key: &ApiKeyLookup<'_>,
+    markdown: bool,
+    on_chunk: Option<&ChunkSink<'_>>,
+    on_step: Option<&StepSink<'_>>,
+) -> Result<RephraseOutcome, RephraseError> {
+    let mut current = text.to_string();
+    let mut outputs: Vec<StepOutput> = Vec::with_capacity(steps.len());
+
+    for (index, step) in steps.iter().enumerate() {
+        eprintln!(
+            "🔗 Pipeline step {}/{}: style={}, provider={}, model={}",
+            index + 1,
+            steps.len(),
+            step.style.id,
+            step.provider,
+            step.model.unwrap_or("default")
+        );
+        let outcome = rephrase_step(registry, client, &current, step, variables, api_key, markdown, on_chunk).await?;
+
+        let output = StepOutput {
+            index,
+            style: step.style.name.clone(),
+            provider: outcome.provider,
+            provider_name: outcome.provider_name,
+            text: outcome.text,
+        };
+        if let Some(on_step) = on_step {
+            on_step(&output);
+        }
+        current = output.text.clone();
+        outputs.push(output);
+    }
+
+    let last = outputs.last().expect("pipeline has at least one step");
+    Ok(RephraseOutcome {
+        text: current,
+        provider: last.provider.clone(),
+        provider_name: last.provider_name.clone(),
+        steps: outputs,
+    })
+}
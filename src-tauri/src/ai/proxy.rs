@@ -0,0 +1,156 @@
+// Proxy server integration (default - uses server-side API key)
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use super::{check_status, Provider, ProviderCapabilities, RephraseError, RephraseRequest, MARKDOWN_INSTRUCTION};
+
+// Heroku is the primary deployment; Render is registered as a separate
+// provider and reached through the default fallback chain.
+const PROXY_URL_PRIMARY: &str = "https://rephraser-technology-21cddf6fbfbc.herokuapp.com/api/rephrase";
+const PROXY_URL_BACKUP: &str = "https://rephraser-9ur5.onrender.com/api/rephrase";
+
+const CLIENT_ID: &str = "desktop/0.1.0";
+
+// Stands in for {{text}} in templates that place the text themselves, since
+// the proxy appends the delimited text on its side
+const TEXT_REFERENCE: &str = "the delimited text";
+
+// The proxy accepts at most this many examples per request
+const MAX_EXAMPLES: usize = 5;
+
+pub struct ProxyProvider {
+    id: &'static str,
+    name: &'static str,
+    url: String,
+}
+
+impl ProxyProvider {
+    pub fn primary() -> Self {
+        Self {
+            id: "proxy",
+            name: "Proxy Server",
+            url: std::env::var("REPHRASER_PROXY_URL").unwrap_or_else(|_| PROXY_URL_PRIMARY.to_string()),
+        }
+    }
+
+    pub fn backup() -> Self {
+        Self {
+            id: "proxy-backup",
+            name: "Proxy Server (Backup)",
+            url: PROXY_URL_BACKUP.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ProxyRequest<'a> {
+    text: &'a str,
+    /// A stock style id, or "custom" with `custom_prompt`.
+    style: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    custom_prompt: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    examples: Vec<ProxyExample<'a>>,
+}
+
+#[derive(Serialize)]
+struct ProxyExample<'a> {
+    input: &'a str,
+    output: &'a str,
+}
+
+#[derive(Deserialize)]
+struct ProxyResponse {
+    rephrased: String,
+}
+
+#[async_trait]
+impl Provider for ProxyProvider {
+    fn id(&self) -> &str {
+        self.id
+    }
+
+    fn display_name(&self) -> &str {
+        self.name
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: false,
+            system_prompt: true,
+            max_input_chars: 10000,
+            models: Vec::new(),
+        }
+    }
+
+    fn requires_api_key(&self) -> bool {
+        false
+    }
+
+    async fn rephrase(&self, client: &Client, request: &RephraseRequest<'_>) -> Result<String, RephraseError> {
+        eprintln!("🌐 Using {} for rephrasing", self.name);
+
+        // The proxy builds the prompt itself, with the same system rules and
+        // delimiters, from the style's instruction and examples
+        let style = request.style;
+        let (style_id, custom_prompt) = if style.is_stock() && !request.markdown && style.examples.is_empty() {
+            (style.id.as_str(), None)
+        } else {
+            ("custom", Some(instruction(request)))
+        };
+
+        let request_body = ProxyRequest {
+            text: request.text,
+            style: style_id,
+            custom_prompt,
+            examples: style
+                .examples
+                .iter()
+                .take(MAX_EXAMPLES)
+                .map(|example| ProxyExample { input: &example.input, output: &example.output })
+                .collect(),
+        };
+
+        eprintln!(
+            "📤 Sending request to proxy: url={}, style={}, text_len={}, examples={}",
+            self.url,
+            style_id,
+            request_body.text.len(),
+            request_body.examples.len()
+        );
+
+        let response = client
+            .post(&self.url)
+            .header("Content-Type", "application/json")
+            .header("X-Rephraser-Client", CLIENT_ID)
+            .json(&request_body)
+            .timeout(request.timeout(60))
+            .send()
+            .await
+            .map_err(|e| {
+                let e = e.without_url();
+                eprintln!("❌ Proxy request failed: {:?}", e);
+                RephraseError::from_request(e)
+            })?;
+
+        let response = check_status(response, self.name).await?;
+
+        let data: ProxyResponse = response.json().await?;
+        eprintln!("✅ Proxy rephrase successful, result_len={}", data.rephrased.len());
+
+        Ok(data.rephrased.trim().to_string())
+    }
+}
+
+// What the style asks for, without the text, plus the Markdown rule for rich selections
+fn instruction(request: &RephraseRequest<'_>) -> String {
+    let style = request.style;
+    let placeholder = if style.uses_text() { TEXT_REFERENCE } else { "" };
+    let mut instruction = style.render(placeholder, request.variables).trim().to_string();
+    if request.markdown {
+        instruction.push_str("\n\n");
+        instruction.push_str(MARKDOWN_INSTRUCTION);
+    }
+    instruction
+}
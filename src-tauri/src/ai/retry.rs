@@ -0,0 +1,210 @@
+// Shared retry policy: jittered exponential backoff that honors server hints
+use rand::Rng;
+use reqwest::header::HeaderMap;
+use std::future::Future;
+use std::time::{Duration, Instant, SystemTime};
+use crate::config::RetrySettings;
+
+use super::RephraseError;
+
+/// Runs `op` until it succeeds, `should_retry` rejects the error, the attempt
+/// limit is hit, or the next wait would exceed the total time budget.
+pub async fn with_retry<F, Fut>(
+    settings: &RetrySettings,
+    provider: &str,
+    should_retry: impl Fn(&RephraseError) -> bool,
+    mut op: F,
+) -> Result<String, RephraseError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<String, RephraseError>>,
+{
+    let started = Instant::now();
+    let budget = Duration::from_secs(settings.budget_secs);
+    let max_attempts = settings.max_attempts.max(1);
+    let mut attempt = 1;
+
+    loop {
+        let error = match op().await {
+            Ok(text) => return Ok(text),
+            Err(e) => e,
+        };
+
+        if attempt >= max_attempts || !should_retry(&error) {
+            return Err(error);
+        }
+
+        let delay = error
+            .retry_after()
+            .unwrap_or_else(|| backoff(settings, attempt));
+        if started.elapsed().saturating_add(delay) > budget {
+            eprintln!("⏱️  {} retry budget exhausted ({}s), giving up", provider, settings.budget_secs);
+            return Err(error);
+        }
+
+        eprintln!(
+            "🔁 {} attempt {}/{} failed ({}), retrying in {}ms",
+            provider, attempt, max_attempts, error.kind(), delay.as_millis()
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+// Exponential backoff with "equal jitter": half the delay is fixed, half random
+fn backoff(settings: &RetrySettings, attempt: u32) -> Duration {
+    let exp = settings
+        .initial_backoff_ms
+        .saturating_mul(1u64 << (attempt - 1).min(16))
+        .min(settings.max_backoff_ms);
+    let half = exp / 2;
+    let jitter = if half > 0 { rand::thread_rng().gen_range(0..=half) } else { 0 };
+    Duration::from_millis(half + jitter)
+}
+
+/// Extracts how long the server asked us to wait, from `Retry-After`
+/// (seconds or HTTP date), `retry-after-ms`, or OpenAI's
+/// `x-ratelimit-reset-*` durations.
+pub fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).map(str::trim);
+
+    if let Some(ms) = header("retry-after-ms").and_then(|v| v.parse::<f64>().ok()) {
+        return Some(Duration::from_millis(ms.max(0.0) as u64));
+    }
+
+    if let Some(value) = header("retry-after") {
+        if let Ok(secs) = value.parse::<f64>() {
+            return Some(Duration::from_millis((secs.max(0.0) * 1000.0) as u64));
+        }
+        if let Ok(at) = httpdate::parse_http_date(value) {
+            return Some(at.duration_since(SystemTime::now()).unwrap_or_default());
+        }
+    }
+
+    ["x-ratelimit-reset-requests", "x-ratelimit-reset-tokens"]
+        .iter()
+        .filter_map(|name| header(name).and_then(parse_go_duration))
+        .max()
+}
+
+// Parses durations like "1s", "6m0s", "250ms" or "1h2m3.5s"
+fn parse_go_duration(value: &str) -> Option<Duration> {
+    let mut total = 0f64;
+    let mut rest = value;
+
+    while !rest.is_empty() {
+        let number_len = rest.find(|c: char| !(c.is_ascii_digit() || c == '.'))?;
+        let number: f64 = rest[..number_len].parse().ok()?;
+        rest = &rest[number_len..];
+
+        let unit_len = rest.find(|c: char| c.is_ascii_digit()).unwrap_or(rest.len());
+        let scale = match &rest[..unit_len] {
+            "h" => 3600.0,
+            "m" => 60.0,
+            "s" => 1.0,
+            "ms" => 0.001,
+            "us" | "µs" => 0.000_001,
+            "ns" => 0.000_000_001,
+            _ => return None,
+        };
+        total += number * scale;
+        rest = &rest[unit_len..];
+    }
+
+    // Out of range for a Duration when a server sends something absurd
+    Duration::try_from_secs_f64(total).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn retry_after_in_seconds() {
+        let parsed = parse_retry_after(&headers(&[("retry-after", "2.5")]));
+        assert_eq!(parsed, Some(Duration::from_millis(2500)));
+    }
+
+    #[test]
+    fn retry_after_ms_wins_over_seconds() {
+        let parsed = parse_retry_after(&headers(&[("retry-after", "10"), ("retry-after-ms", "150")]));
+        assert_eq!(parsed, Some(Duration::from_millis(150)));
+    }
+
+    #[test]
+    fn retry_after_as_http_date() {
+        let at = SystemTime::now() + Duration::from_secs(30);
+        let parsed = parse_retry_after(&headers(&[("retry-after", &httpdate::fmt_http_date(at))])).unwrap();
+        // The date has one-second precision
+        assert!(parsed > Duration::from_secs(28) && parsed <= Duration::from_secs(30), "{:?}", parsed);
+    }
+
+    #[test]
+    fn retry_after_date_in_the_past_means_now() {
+        let parsed = parse_retry_after(&headers(&[("retry-after", "Wed, 21 Oct 2015 07:28:00 GMT")]));
+        assert_eq!(parsed, Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn openai_reset_durations_take_the_longest() {
+        let parsed = parse_retry_after(&headers(&[
+            ("x-ratelimit-reset-requests", "1s"),
+            ("x-ratelimit-reset-tokens", "6m0s"),
+        ]));
+        assert_eq!(parsed, Some(Duration::from_secs(360)));
+    }
+
+    #[test]
+    fn go_durations() {
+        assert_eq!(parse_go_duration("250ms"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_go_duration("1h2m3.5s"), Some(Duration::from_millis(3_723_500)));
+        assert_eq!(parse_go_duration("20µs"), Some(Duration::from_micros(20)));
+    }
+
+    #[test]
+    fn garbage_is_ignored() {
+        assert_eq!(parse_retry_after(&headers(&[("retry-after", "soon")])), None);
+        assert_eq!(parse_retry_after(&headers(&[("x-ratelimit-reset-tokens", "5 minutes")])), None);
+        assert_eq!(parse_go_duration("5x"), None);
+        assert_eq!(parse_go_duration("s"), None);
+        assert_eq!(parse_retry_after(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn oversized_go_duration_does_not_panic() {
+        let huge = format!("{}h", "9".repeat(40));
+        assert_eq!(parse_go_duration(&huge), None);
+        assert_eq!(parse_retry_after(&headers(&[("x-ratelimit-reset-requests", &huge)])), None);
+    }
+
+    #[test]
+    fn backoff_stays_within_bounds() {
+        let settings = RetrySettings::default();
+        for attempt in 1..=40 {
+            let expected = (settings.initial_backoff_ms << (attempt - 1).min(16)).min(settings.max_backoff_ms);
+            for _ in 0..20 {
+                let delay = backoff(&settings, attempt).as_millis() as u64;
+                assert!(delay >= expected / 2 && delay <= expected, "attempt {}: {}ms", attempt, delay);
+            }
+        }
+    }
+
+    #[test]
+    fn backoff_saturates_on_large_settings() {
+        let settings = RetrySettings {
+            initial_backoff_ms: u64::MAX,
+            max_backoff_ms: u64::MAX,
+            ..RetrySettings::default()
+        };
+        assert!(backoff(&settings, 30) >= Duration::from_millis(u64::MAX / 2));
+    }
+}
@@ -0,0 +1,138 @@
+// Minimal Server-Sent Events reader for streaming LLM responses
+use reqwest::Response;
+
+use super::{ChunkSink, RephraseError};
+
+/// Incremental SSE decoder. Bytes are buffered until a full line is available
+/// so multi-byte UTF-8 characters split across network chunks stay intact.
+#[derive(Default)]
+struct SseDecoder {
+    buffer: Vec<u8>,
+    data: Vec<String>,
+}
+
+impl SseDecoder {
+    /// Feeds raw bytes and returns the `data` payload of every completed event.
+    fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(bytes);
+        let mut events = Vec::new();
+
+        while let Some(pos) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\r', '\n']);
+
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(self.data.join("\n"));
+                    self.data.clear();
+                }
+            } else if let Some(value) = line.strip_prefix("data:") {
+                self.data.push(value.strip_prefix(' ').unwrap_or(value).to_string());
+            }
+            // `event:`, `id:`, `retry:` and `:` comments carry nothing we need
+        }
+
+        events
+    }
+
+    /// Flushes an event left open when the stream ends without a blank line.
+    fn finish(&mut self) -> Option<String> {
+        self.push(b"\n\n").pop()
+    }
+}
+
+/// Reads an SSE response to the end, forwarding each text delta to `on_chunk`.
+///
+/// `extract` turns one event payload into the text delta it carries, if any.
+/// Returns the concatenated text of all deltas.
+pub async fn read_stream<F>(
+    mut response: Response,
+    provider: &str,
+    on_chunk: &ChunkSink<'_>,
+    extract: F,
+) -> Result<String, RephraseError>
+where
+    F: Fn(&str) -> Result<Option<String>, RephraseError> + Send + Sync,
+{
+    let mut decoder = SseDecoder::default();
+    let mut text = String::new();
+
+    let handle = |payload: String, text: &mut String| -> Result<(), RephraseError> {
+        if payload == "[DONE]" {
+            return Ok(());
+        }
+        if let Some(delta) = extract(&payload)? {
+            if !delta.is_empty() {
+                on_chunk(&delta);
+                text.push_str(&delta);
+            }
+        }
+        Ok(())
+    };
+
+    while let Some(chunk) = response.chunk().await.map_err(|e| {
+        let e = e.without_url();
+        eprintln!("❌ {} stream interrupted: {:?}", provider, e);
+        RephraseError::from_request(e)
+    })? {
+        for payload in decoder.push(&chunk) {
+            handle(payload, &mut text)?;
+        }
+    }
+    if let Some(payload) = decoder.finish() {
+        handle(payload, &mut text)?;
+    }
+
+    eprintln!("📥 {} stream finished, result_len={}", provider, text.len());
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Feeds the pieces in order and collects every event, including
+    // the one flushed at the end
+    fn decode(pieces: &[&[u8]]) -> Vec<String> {
+        let mut decoder = SseDecoder::default();
+        let mut events: Vec<String> = pieces.iter().flat_map(|piece| decoder.push(piece)).collect();
+        events.extend(decoder.finish());
+        events
+    }
+
+    #[test]
+    fn decodes_events() {
+        let stream: &[u8] = b": keep-alive\nevent: message\ndata: {\"a\":1}\n\ndata:{\"b\":2}\n\ndata: [DONE]\n\n";
+        assert_eq!(decode(&[stream]), vec!["{\"a\":1}", "{\"b\":2}", "[DONE]"]);
+    }
+
+    #[test]
+    fn keeps_characters_split_across_chunks() {
+        let stream = "data: héllo 👋\n\n".as_bytes();
+        // Inside the two-byte é, then inside the four-byte emoji
+        let e_split = "data: h".len() + 1;
+        let emoji_split = "data: héllo ".len() + 2;
+        let pieces = [&stream[..e_split], &stream[e_split..emoji_split], &stream[emoji_split..]];
+        assert_eq!(decode(&pieces), vec!["héllo 👋"]);
+    }
+
+    #[test]
+    fn handles_crlf_split_across_chunks() {
+        let pieces: [&[u8]; 4] = [b"data: one\r", b"\n\r", b"\ndata: two\r\n", b"\r\n"];
+        assert_eq!(decode(&pieces), vec!["one", "two"]);
+    }
+
+    #[test]
+    fn joins_multi_line_data() {
+        assert_eq!(decode(&[b"data: first\ndata: second\n\n"]), vec!["first\nsecond"]);
+    }
+
+    #[test]
+    fn flushes_a_final_event_without_a_blank_line() {
+        assert_eq!(decode(&[b"data: one\n\ndata: last\n"]), vec!["one", "last"]);
+        assert_eq!(decode(&[b"data: one\n\ndata: last"]), vec!["one", "last"]);
+        assert_eq!(decode(&[b"data: one\n\n"]), vec!["one"]);
+        assert!(decode(&[b""]).is_empty());
+    }
+}
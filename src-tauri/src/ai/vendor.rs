@@ -0,0 +1,227 @@
+// Vendor error bodies: pulls the error type and message out of non-2xx replies
+use serde::Deserialize;
+use serde_json::Value;
+use std::fmt;
+
+// Longest body excerpt kept when a reply is not JSON (e.g. an HTML gateway page)
+const MAX_PLAIN_BODY: usize = 300;
+
+// Prefixes of API keys issued by the providers we talk to
+const KEY_PREFIXES: &[&str] = &["sk-", "sk_", "pplx-", "AIza", "gsk_", "xai-", "key-"];
+
+/// Error type and message reported by the provider, with keys redacted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VendorError {
+    /// `invalid_request_error`, `insufficient_quota`, `INVALID_ARGUMENT`, ...
+    pub kind: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for VendorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            Some(kind) => write!(f, "{}: {}", kind, self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+// Covers the shapes used by our providers:
+//   OpenAI, Perplexity, OpenAI-compatible servers: {"error": {"message", "type", "code"}}
+//   Anthropic:  {"type": "error", "error": {"type", "message"}}
+//   Gemini:     {"error": {"code", "message", "status"}}, sometimes wrapped in an array
+//   Proxy:      {"error": "message"} or {"message": "..."} / {"detail": "..."}
+#[derive(Deserialize)]
+struct Envelope {
+    #[serde(default)]
+    error: Option<Value>,
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    detail: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Detail {
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default, rename = "type")]
+    kind: Option<String>,
+    #[serde(default)]
+    status: Option<String>,
+    #[serde(default)]
+    code: Option<Value>,
+}
+
+/// Extracts the vendor error from a response body. Falls back to a short
+/// excerpt of the raw text when the body is not a recognized JSON error.
+pub fn parse(body: &str) -> Option<VendorError> {
+    let body = body.trim();
+    if body.is_empty() {
+        return None;
+    }
+
+    let parsed = match serde_json::from_str::<Value>(body) {
+        Ok(Value::Array(items)) => items.into_iter().next().and_then(from_json),
+        Ok(value) => from_json(value),
+        Err(_) => None,
+    };
+
+    parsed.or_else(|| {
+        let excerpt: String = body.chars().take(MAX_PLAIN_BODY).collect();
+        Some(VendorError { kind: None, message: redact(&excerpt) })
+    })
+}
+
+fn from_json(value: Value) -> Option<VendorError> {
+    let envelope: Envelope = serde_json::from_value(value).ok()?;
+
+    let (kind, message) = match envelope.error {
+        Some(Value::String(message)) => (None, Some(message)),
+        Some(error @ Value::Object(_)) => {
+            let detail: Detail = serde_json::from_value(error).ok()?;
+            let code = detail.code.and_then(|c| c.as_str().map(str::to_string));
+            (detail.kind.or(detail.status).or(code), detail.message)
+        }
+        _ => (None, envelope.message.or(envelope.detail)),
+    };
+
+    let message = message.filter(|m| !m.trim().is_empty())?;
+    Some(VendorError {
+        kind: kind.filter(|k| !k.trim().is_empty()),
+        message: redact(message.trim()),
+    })
+}
+
+/// Masks anything that looks like an API key or bearer token, keeping the
+/// first four characters so the user can still tell which key was meant.
+pub fn redact(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut token = String::new();
+
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+            token.push(c);
+        } else {
+            flush_token(&mut out, &mut token);
+            out.push(c);
+        }
+    }
+    flush_token(&mut out, &mut token);
+    out
+}
+
+fn flush_token(out: &mut String, token: &mut String) {
+    if looks_like_key(token) {
+        out.push_str(&token[..4]);
+        out.push_str("…[redacted]");
+    } else {
+        out.push_str(token);
+    }
+    token.clear();
+}
+
+fn looks_like_key(token: &str) -> bool {
+    if KEY_PREFIXES.iter().any(|p| token.starts_with(p)) && token.len() >= 16 {
+        return true;
+    }
+    // Long opaque tokens mixing letters and digits (hex or base64url secrets)
+    token.len() >= 32
+        && token.chars().any(|c| c.is_ascii_digit())
+        && token.chars().any(|c| c.is_ascii_alphabetic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_provider_keys() {
+        assert_eq!(
+            redact("Incorrect API key provided: sk-proj-abcdefghijklmnop1234."),
+            "Incorrect API key provided: sk-p…[redacted]."
+        );
+        assert_eq!(
+            redact("invalid x-api-key sk-ant-REDACTED"),
+            "invalid x-api-key sk-a…[redacted]"
+        );
+        assert_eq!(
+            redact("API key not valid: AIzaSyA1b2C3d4E5f6G7h8I9j0"),
+            "API key not valid: AIza…[redacted]"
+        );
+        assert_eq!(
+            redact("Bearer 0123456789abcdef0123456789abcdef was rejected"),
+            "Bearer 0123…[redacted] was rejected"
+        );
+    }
+
+    #[test]
+    fn leaves_short_tokens_and_words_alone() {
+        let text = "Rate limit reached for gpt-4o-mini in organization org-abc on tokens per min.";
+        assert_eq!(redact(text), text);
+        assert_eq!(redact("sk-short"), "sk-short");
+        assert_eq!(redact("key-value pairs"), "key-value pairs");
+        assert_eq!(redact("internationalization_and_localization"), "internationalization_and_localization");
+    }
+
+    #[test]
+    fn parses_openai_errors() {
+        let body = r#"{"error": {"message": "You exceeded your current quota.", "type": "insufficient_quota", "param": null, "code": "insufficient_quota"}}"#;
+        assert_eq!(
+            parse(body),
+            Some(VendorError {
+                kind: Some("insufficient_quota".to_string()),
+                message: "You exceeded your current quota.".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn parses_anthropic_errors() {
+        let body = r#"{"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}}"#;
+        assert_eq!(
+            parse(body),
+            Some(VendorError { kind: Some("overloaded_error".to_string()), message: "Overloaded".to_string() })
+        );
+    }
+
+    #[test]
+    fn parses_gemini_errors() {
+        let body = r#"[{"error": {"code": 400, "message": "API key not valid. Please pass a valid API key.", "status": "INVALID_ARGUMENT"}}]"#;
+        assert_eq!(
+            parse(body),
+            Some(VendorError {
+                kind: Some("INVALID_ARGUMENT".to_string()),
+                message: "API key not valid. Please pass a valid API key.".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn parses_proxy_and_plain_errors() {
+        assert_eq!(
+            parse(r#"{"error": "Rate limit exceeded. Please wait a moment and try again."}"#),
+            Some(VendorError { kind: None, message: "Rate limit exceeded. Please wait a moment and try again.".to_string() })
+        );
+        assert_eq!(
+            parse(r#"{"detail": "Not authenticated"}"#),
+            Some(VendorError { kind: None, message: "Not authenticated".to_string() })
+        );
+        assert_eq!(
+            parse(r#"{"error": {"message": "Model not found", "code": "model_not_found"}}"#).unwrap().kind.as_deref(),
+            Some("model_not_found")
+        );
+    }
+
+    #[test]
+    fn falls_back_to_a_redacted_excerpt() {
+        let page = format!("<html>Bad gateway for sk-abcdefghijklmnopqrst {}</html>", "x".repeat(400));
+        let parsed = parse(&page).unwrap();
+        assert_eq!(parsed.kind, None);
+        assert!(parsed.message.starts_with("<html>Bad gateway for sk-a…[redacted] "), "{}", parsed.message);
+        assert!(parsed.message.chars().count() < page.chars().count());
+
+        assert_eq!(parse("   "), None);
+        assert_eq!(parse(r#"{"error": {"message": "  "}}"#).unwrap().message, r#"{"error": {"message": "  "}}"#);
+    }
+}
@@ -0,0 +1,79 @@
+// Shared HTTP client so every provider call reuses pooled connections and TLS sessions
+use reqwest::{Certificate, Client, NoProxy, Proxy};
+use std::fs;
+use std::sync::RwLock;
+use std::time::Duration;
+use crate::config::NetworkSettings;
+
+const DEFAULT_USER_AGENT: &str = concat!("Rephraser/", env!("CARGO_PKG_VERSION"));
+
+pub struct HttpClient {
+    client: RwLock<Client>,
+}
+
+impl HttpClient {
+    /// Handle to the current client. Cheap: clones share the connection pool.
+    pub fn get(&self) -> Client {
+        self.client.read().unwrap().clone()
+    }
+
+    /// Rebuilds the client from new settings. On error the previous client
+    /// stays in place, so a typo in Settings never leaves the app offline.
+    pub fn configure(&self, settings: &NetworkSettings) -> Result<(), String> {
+        let client = build(settings)?;
+        *self.client.write().unwrap() = client;
+        eprintln!(
+            "🌐 HTTP client configured: proxy={}, extra_cas={}",
+            settings.https_proxy.is_some(),
+            settings.ca_certificates.len()
+        );
+        Ok(())
+    }
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        let client = build(&NetworkSettings::default()).unwrap_or_default();
+        Self { client: RwLock::new(client) }
+    }
+}
+
+fn build(settings: &NetworkSettings) -> Result<Client, String> {
+    let user_agent = settings
+        .user_agent
+        .as_deref()
+        .filter(|ua| !ua.trim().is_empty())
+        .unwrap_or(DEFAULT_USER_AGENT);
+
+    let mut builder = Client::builder()
+        .user_agent(user_agent)
+        .connect_timeout(Duration::from_secs(settings.connect_timeout_secs))
+        .read_timeout(Duration::from_secs(settings.read_timeout_secs))
+        .pool_idle_timeout(Duration::from_secs(90))
+        .tcp_keepalive(Duration::from_secs(60));
+
+    if let Some(url) = settings.https_proxy.as_deref().filter(|u| !u.trim().is_empty()) {
+        // Credentials embedded in the URL are sent as Proxy-Authorization
+        let proxy = Proxy::https(url.trim())
+            .map_err(|e| format!("Invalid proxy URL: {}", e))?
+            .no_proxy(NoProxy::from_string(&settings.no_proxy.join(",")));
+        builder = builder.proxy(proxy);
+    }
+
+    for path in &settings.ca_certificates {
+        let pem = fs::read(path)
+            .map_err(|e| format!("Failed to read CA certificate {}: {}", path, e))?;
+        let certs = Certificate::from_pem_bundle(&pem)
+            .map_err(|e| format!("Invalid CA certificate {}: {}", path, e))?;
+        if certs.is_empty() {
+            return Err(format!("No certificates found in {}", path));
+        }
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    builder
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {}", e))
+}
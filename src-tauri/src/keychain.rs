@@ -1,16 +1,57 @@
 use std::error::Error;
+use crate::config::CredentialStore;
+
+pub mod file_store;
+#[cfg(target_os = "linux")]
+mod secret_service;
 
 const SERVICE_NAME: &str = "com.rephraser.desktop";
 
-#[cfg(target_os = "macos")]
+/// Single slot shared by every provider before keys were stored per provider.
+/// Only read during migration.
+pub const LEGACY_API_KEY_ACCOUNT: &str = "api_key";
+
+/// Account holding the API key for a provider or custom endpoint id.
+pub fn api_key_account(provider_id: &str) -> String {
+    format!("api_key.{}", provider_id.to_lowercase())
+}
+
+/// Chooses where secrets live. The encrypted file store is opt-in for
+/// systems without an OS keychain (headless Linux, minimal window managers).
+pub fn use_store(store: CredentialStore) {
+    file_store::set_enabled(store == CredentialStore::EncryptedFile);
+}
+
 pub fn set(account: &str, secret: &str) -> Result<(), Box<dyn Error>> {
+    if file_store::is_enabled() {
+        return file_store::set(account, secret);
+    }
+    system_set(account, secret)
+}
+
+pub fn get(account: &str) -> Result<Option<String>, Box<dyn Error>> {
+    if file_store::is_enabled() {
+        return file_store::get(account);
+    }
+    system_get(account)
+}
+
+pub fn delete(account: &str) -> Result<(), Box<dyn Error>> {
+    if file_store::is_enabled() {
+        return file_store::delete(account);
+    }
+    system_delete(account)
+}
+
+#[cfg(target_os = "macos")]
+fn system_set(account: &str, secret: &str) -> Result<(), Box<dyn Error>> {
     use security_framework::passwords::set_generic_password;
     set_generic_password(SERVICE_NAME, account, secret.as_bytes())?;
     Ok(())
 }
 
 #[cfg(target_os = "macos")]
-pub fn get(account: &str) -> Result<Option<String>, Box<dyn Error>> {
+fn system_get(account: &str) -> Result<Option<String>, Box<dyn Error>> {
     use security_framework::passwords::get_generic_password;
     match get_generic_password(SERVICE_NAME, account) {
         Ok(bytes) => {
@@ -39,7 +80,7 @@ pub fn get(account: &str) -> Result<Option<String>, Box<dyn Error>> {
 }
 
 #[cfg(target_os = "macos")]
-pub fn delete(account: &str) -> Result<(), Box<dyn Error>> {
+fn system_delete(account: &str) -> Result<(), Box<dyn Error>> {
     use security_framework::passwords::delete_generic_password;
     match delete_generic_password(SERVICE_NAME, account) {
         Ok(()) => Ok(()),
@@ -59,7 +100,7 @@ pub fn delete(account: &str) -> Result<(), Box<dyn Error>> {
 }
 
 #[cfg(target_os = "windows")]
-pub fn set(account: &str, secret: &str) -> Result<(), Box<dyn Error>> {
+fn system_set(account: &str, secret: &str) -> Result<(), Box<dyn Error>> {
     use std::ptr;
     use windows::Win32::Security::Credentials::*;
     use windows::core::PWSTR;
@@ -86,7 +127,7 @@ pub fn set(account: &str, secret: &str) -> Result<(), Box<dyn Error>> {
 }
 
 #[cfg(target_os = "windows")]
-pub fn get(account: &str) -> Result<Option<String>, Box<dyn Error>> {
+fn system_get(account: &str) -> Result<Option<String>, Box<dyn Error>> {
     use windows::Win32::Security::Credentials::*;
     use windows::core::PCWSTR;
 
@@ -124,7 +165,7 @@ pub fn get(account: &str) -> Result<Option<String>, Box<dyn Error>> {
 }
 
 #[cfg(target_os = "windows")]
-pub fn delete(account: &str) -> Result<(), Box<dyn Error>> {
+fn system_delete(account: &str) -> Result<(), Box<dyn Error>> {
     use windows::Win32::Security::Credentials::*;
     use windows::core::PCWSTR;
 
@@ -147,17 +188,32 @@ pub fn delete(account: &str) -> Result<(), Box<dyn Error>> {
     }
 }
 
-#[cfg(not(any(target_os = "macos", target_os = "windows")))]
-pub fn set(_account: &str, _secret: &str) -> Result<(), Box<dyn Error>> {
-    Err("Secure credential storage not available on this platform".into())
+#[cfg(target_os = "linux")]
+fn system_set(account: &str, secret: &str) -> Result<(), Box<dyn Error>> {
+    secret_service::set(account, secret)
+}
+
+#[cfg(target_os = "linux")]
+fn system_get(account: &str) -> Result<Option<String>, Box<dyn Error>> {
+    secret_service::get(account)
+}
+
+#[cfg(target_os = "linux")]
+fn system_delete(account: &str) -> Result<(), Box<dyn Error>> {
+    secret_service::delete(account)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+fn system_set(_account: &str, _secret: &str) -> Result<(), Box<dyn Error>> {
+    Err("Secure credential storage not available on this platform. Enable the encrypted file store in config.json.".into())
 }
 
-#[cfg(not(any(target_os = "macos", target_os = "windows")))]
-pub fn get(_account: &str) -> Result<Option<String>, Box<dyn Error>> {
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+fn system_get(_account: &str) -> Result<Option<String>, Box<dyn Error>> {
     Ok(None)
 }
 
-#[cfg(not(any(target_os = "macos", target_os = "windows")))]
-pub fn delete(_account: &str) -> Result<(), Box<dyn Error>> {
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+fn system_delete(_account: &str) -> Result<(), Box<dyn Error>> {
     Ok(())
 }
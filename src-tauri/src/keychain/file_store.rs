@@ -0,0 +1,413 @@
+// Opt-in passphrase-encrypted credential file for systems without an OS keychain
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use zeroize::Zeroizing;
+use crate::config;
+
+const FILE_NAME: &str = "credentials.enc";
+const FORMAT_VERSION: u32 = 1;
+
+/// Lets headless setups unlock the store without the UI.
+pub const PASSPHRASE_ENV: &str = "REPHRASER_KEYSTORE_PASSPHRASE";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static STORE: Store = Store::in_config_dir();
+
+// Derived key kept in memory after unlocking, so Argon2 runs once per session
+struct UnlockedKey {
+    key: Zeroizing<[u8; 32]>,
+    salt: Vec<u8>,
+}
+
+// On-disk layout: one AEAD-sealed JSON map of account -> secret
+#[derive(Serialize, Deserialize)]
+struct StoreFile {
+    version: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+type Entries = HashMap<String, String>;
+
+// The credential file and the key that opens it
+struct Store {
+    /// Directory holding the file; the app's config directory when `None`.
+    dir: Option<PathBuf>,
+    unlocked: Mutex<Option<UnlockedKey>>,
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+    if !enabled {
+        lock();
+    }
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn is_unlocked() -> bool {
+    STORE.is_unlocked()
+}
+
+/// Whether the store has been created, i.e. `unlock` rather than `create` applies.
+pub fn exists() -> Result<bool, Box<dyn Error>> {
+    Ok(STORE.read_file()?.is_some())
+}
+
+/// Creates an empty store sealed with `passphrase`, which has to be typed
+/// twice so a typo cannot become the passphrase, and leaves it unlocked.
+pub fn create(passphrase: &str, confirmation: &str) -> Result<(), Box<dyn Error>> {
+    STORE.create(passphrase, confirmation)
+}
+
+/// Derives the key from `passphrase` and checks it against the existing file.
+pub fn unlock(passphrase: &str) -> Result<(), Box<dyn Error>> {
+    STORE.unlock(passphrase)
+}
+
+pub fn lock() {
+    STORE.lock();
+}
+
+pub fn set(account: &str, secret: &str) -> Result<(), Box<dyn Error>> {
+    STORE.set(account, secret)
+}
+
+pub fn get(account: &str) -> Result<Option<String>, Box<dyn Error>> {
+    STORE.get(account)
+}
+
+pub fn delete(account: &str) -> Result<(), Box<dyn Error>> {
+    STORE.delete(account)
+}
+
+impl Store {
+    const fn in_config_dir() -> Self {
+        Self { dir: None, unlocked: Mutex::new(None) }
+    }
+
+    fn is_unlocked(&self) -> bool {
+        self.unlocked.lock().unwrap().is_some()
+    }
+
+    fn create(&self, passphrase: &str, confirmation: &str) -> Result<(), Box<dyn Error>> {
+        if passphrase.is_empty() {
+            return Err("Passphrase cannot be empty".into());
+        }
+        if passphrase != confirmation {
+            return Err("The passphrases do not match".into());
+        }
+        if self.read_file()?.is_some() {
+            return Err("The credential store already exists. Unlock it with its passphrase.".into());
+        }
+
+        let mut salt = vec![0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let unlocked = UnlockedKey { key: derive_key(passphrase, &salt)?, salt };
+        // Sealed right away, so the next unlock is checked against it
+        self.write_file(&encrypt(&unlocked, &Entries::new())?)?;
+        *self.unlocked.lock().unwrap() = Some(unlocked);
+        Ok(())
+    }
+
+    fn unlock(&self, passphrase: &str) -> Result<(), Box<dyn Error>> {
+        if passphrase.is_empty() {
+            return Err("Passphrase cannot be empty".into());
+        }
+        let file = self
+            .read_file()?
+            .ok_or("There is no credential store yet. Choose a passphrase to create it.")?;
+
+        let salt = BASE64.decode(&file.salt)?;
+        let unlocked = UnlockedKey { key: derive_key(passphrase, &salt)?, salt };
+        decrypt(&unlocked, &file).map_err(|_| "Incorrect passphrase for the credential store")?;
+        *self.unlocked.lock().unwrap() = Some(unlocked);
+        Ok(())
+    }
+
+    fn lock(&self) {
+        self.unlocked.lock().unwrap().take();
+    }
+
+    fn set(&self, account: &str, secret: &str) -> Result<(), Box<dyn Error>> {
+        self.with_entries(|entries| {
+            entries.insert(account.to_string(), secret.to_string());
+            true
+        })
+    }
+
+    fn get(&self, account: &str) -> Result<Option<String>, Box<dyn Error>> {
+        let mut value = None;
+        self.with_entries(|entries| {
+            value = entries.get(account).filter(|v| !v.is_empty()).cloned();
+            false
+        })?;
+        Ok(value)
+    }
+
+    fn delete(&self, account: &str) -> Result<(), Box<dyn Error>> {
+        self.with_entries(|entries| entries.remove(account).is_some())
+    }
+
+    // Decrypts the store, lets `f` edit it, and re-encrypts when `f` returns true
+    fn with_entries(&self, f: impl FnOnce(&mut Entries) -> bool) -> Result<(), Box<dyn Error>> {
+        self.ensure_unlocked()?;
+        let guard = self.unlocked.lock().unwrap();
+        let unlocked = guard
+            .as_ref()
+            .ok_or("Credential store is locked")?;
+
+        let mut entries = match self.read_file()? {
+            Some(file) => decrypt(unlocked, &file)?,
+            None => Entries::new(),
+        };
+
+        if f(&mut entries) {
+            self.write_file(&encrypt(unlocked, &entries)?)?;
+        }
+        Ok(())
+    }
+
+    // Headless setups pass the passphrase in the environment, which also
+    // creates the store on first use since it cannot be mistyped interactively
+    fn ensure_unlocked(&self) -> Result<(), Box<dyn Error>> {
+        if self.is_unlocked() {
+            return Ok(());
+        }
+        match std::env::var(PASSPHRASE_ENV) {
+            Ok(passphrase) if !passphrase.is_empty() => match self.read_file()? {
+                Some(_) => self.unlock(&passphrase),
+                None => self.create(&passphrase, &passphrase),
+            },
+            _ => Err(format!(
+                "Credential store is locked. Unlock it in Settings or set {}.",
+                PASSPHRASE_ENV
+            )
+            .into()),
+        }
+    }
+
+    fn path(&self) -> Result<PathBuf, Box<dyn Error>> {
+        let dir = match &self.dir {
+            Some(dir) => dir.clone(),
+            None => config::config_dir()?,
+        };
+        Ok(dir.join(FILE_NAME))
+    }
+
+    fn read_file(&self) -> Result<Option<StoreFile>, Box<dyn Error>> {
+        let path = self.path()?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&fs::read_to_string(path)?)?))
+    }
+
+    // Writes to a temp file and renames it so a crash never leaves a torn store
+    fn write_file(&self, file: &StoreFile) -> Result<(), Box<dyn Error>> {
+        let path = self.path()?;
+        let tmp = path.with_extension("enc.tmp");
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options
+            .open(&tmp)?
+            .write_all(serde_json::to_string_pretty(file)?.as_bytes())?;
+
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Zeroizing<[u8; 32]>, Box<dyn Error>> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+        .map_err(|e| format!("Failed to derive key: {}", e))?;
+    Ok(key)
+}
+
+fn encrypt(unlocked: &UnlockedKey, entries: &Entries) -> Result<StoreFile, Box<dyn Error>> {
+    let cipher = XChaCha20Poly1305::new(unlocked.key.as_ref().into());
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let plaintext = Zeroizing::new(serde_json::to_vec(entries)?);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_slice())
+        .map_err(|_| "Failed to encrypt credential store")?;
+
+    Ok(StoreFile {
+        version: FORMAT_VERSION,
+        salt: BASE64.encode(&unlocked.salt),
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    })
+}
+
+fn decrypt(unlocked: &UnlockedKey, file: &StoreFile) -> Result<Entries, Box<dyn Error>> {
+    if file.version != FORMAT_VERSION {
+        return Err(format!("Unsupported credential store version: {}", file.version).into());
+    }
+
+    let nonce = BASE64.decode(&file.nonce)?;
+    if nonce.len() != 24 {
+        return Err("Corrupt credential store: bad nonce".into());
+    }
+    let cipher = XChaCha20Poly1305::new(unlocked.key.as_ref().into());
+    let plaintext = Zeroizing::new(
+        cipher
+            .decrypt(XNonce::from_slice(&nonce), BASE64.decode(&file.ciphertext)?.as_slice())
+            .map_err(|_| "Failed to decrypt credential store")?,
+    );
+    Ok(serde_json::from_slice(&plaintext)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_for(passphrase: &str, salt: &[u8]) -> UnlockedKey {
+        UnlockedKey { key: derive_key(passphrase, salt).unwrap(), salt: salt.to_vec() }
+    }
+
+    fn entries(pairs: &[(&str, &str)]) -> Entries {
+        pairs.iter().map(|(account, secret)| (account.to_string(), secret.to_string())).collect()
+    }
+
+    #[test]
+    fn encrypt_decrypt_round_trip() {
+        let unlocked = key_for("correct horse", b"0123456789abcdef");
+        let stored = entries(&[("api_key.openai", "sk-test"), ("api_key.claude", "sk-ant-test")]);
+
+        let file = encrypt(&unlocked, &stored).unwrap();
+        assert_eq!(BASE64.decode(&file.salt).unwrap(), b"0123456789abcdef");
+        assert!(!file.ciphertext.contains("sk-test"));
+        assert_eq!(decrypt(&unlocked, &file).unwrap(), stored);
+    }
+
+    #[test]
+    fn each_encryption_uses_a_fresh_nonce() {
+        let unlocked = key_for("correct horse", b"0123456789abcdef");
+        let stored = entries(&[("api_key.openai", "sk-test")]);
+        assert_ne!(encrypt(&unlocked, &stored).unwrap().nonce, encrypt(&unlocked, &stored).unwrap().nonce);
+    }
+
+    #[test]
+    fn wrong_passphrase_cannot_decrypt() {
+        let salt = b"0123456789abcdef";
+        let file = encrypt(&key_for("correct horse", salt), &entries(&[("api_key.openai", "sk-test")])).unwrap();
+        assert!(decrypt(&key_for("battery staple", salt), &file).is_err());
+    }
+
+    #[test]
+    fn tampered_or_unknown_files_are_rejected() {
+        let unlocked = key_for("correct horse", b"0123456789abcdef");
+        let file = encrypt(&unlocked, &entries(&[("api_key.openai", "sk-test")])).unwrap();
+
+        let mut ciphertext = BASE64.decode(&file.ciphertext).unwrap();
+        ciphertext[0] ^= 1;
+        let tampered = StoreFile { ciphertext: BASE64.encode(ciphertext), ..file };
+        assert!(decrypt(&unlocked, &tampered).is_err());
+
+        let future = StoreFile { version: FORMAT_VERSION + 1, ..encrypt(&unlocked, &Entries::new()).unwrap() };
+        assert!(decrypt(&unlocked, &future).is_err());
+
+        let short_nonce = StoreFile { nonce: BASE64.encode([0u8; 12]), ..encrypt(&unlocked, &Entries::new()).unwrap() };
+        assert!(decrypt(&unlocked, &short_nonce).is_err());
+    }
+
+    // A store of its own in a fresh directory, leaving the app's alone
+    struct TempStore {
+        store: Store,
+        dir: PathBuf,
+    }
+
+    impl TempStore {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("rephraser-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Self {
+                store: Store { dir: Some(dir.clone()), unlocked: Mutex::new(None) },
+                dir,
+            }
+        }
+    }
+
+    impl Drop for TempStore {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn unlock_checks_the_passphrase_against_the_file() {
+        let temp = TempStore::new("unlock");
+        let store = &temp.store;
+
+        store.create("correct horse", "correct horse").unwrap();
+        store.set("api_key.openai", "sk-test").unwrap();
+        assert_eq!(store.get("api_key.openai").unwrap().as_deref(), Some("sk-test"));
+        assert_eq!(store.get("api_key.claude").unwrap(), None);
+
+        store.lock();
+        assert!(!store.is_unlocked());
+        let error = store.unlock("battery staple").unwrap_err();
+        assert_eq!(error.to_string(), "Incorrect passphrase for the credential store");
+        assert!(!store.is_unlocked());
+        assert!(store.unlock("").is_err());
+
+        store.unlock("correct horse").unwrap();
+        assert_eq!(store.get("api_key.openai").unwrap().as_deref(), Some("sk-test"));
+        store.delete("api_key.openai").unwrap();
+        assert_eq!(store.get("api_key.openai").unwrap(), None);
+    }
+
+    #[test]
+    fn creating_needs_a_matching_confirmation() {
+        let temp = TempStore::new("create");
+        let store = &temp.store;
+
+        assert!(store.create("", "").is_err());
+        let error = store.create("correct horse", "correct hrose").unwrap_err();
+        assert_eq!(error.to_string(), "The passphrases do not match");
+        assert!(store.read_file().unwrap().is_none());
+        assert!(!store.is_unlocked());
+
+        store.create("correct horse", "correct horse").unwrap();
+        assert!(store.is_unlocked());
+        assert!(store.create("other", "other").is_err());
+
+        // The empty store is sealed at once, so a mistyped unlock is caught
+        store.lock();
+        assert!(store.unlock("correct hrose").is_err());
+        store.unlock("correct horse").unwrap();
+    }
+
+    #[test]
+    fn unlocking_needs_an_existing_store() {
+        let temp = TempStore::new("missing");
+        let error = temp.store.unlock("correct horse").unwrap_err();
+        assert!(error.to_string().starts_with("There is no credential store yet"), "{}", error);
+        assert!(!temp.store.is_unlocked());
+    }
+}
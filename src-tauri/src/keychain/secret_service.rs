@@ -0,0 +1,210 @@
+// Linux backend: freedesktop Secret Service over D-Bus (gnome-keyring, KWallet, KeePassXC)
+use secret_service::blocking::{Collection, SecretService};
+use secret_service::{EncryptionType, Error as SsError};
+use std::collections::HashMap;
+use std::error::Error;
+
+use super::SERVICE_NAME;
+
+type Attributes<'a> = HashMap<&'a str, &'a str>;
+
+// The few keyring operations the backend needs, so its logic can run against
+// a fake instead of a session bus
+trait Keyring {
+    /// Creates the item, replacing any with the same attributes.
+    fn replace(&self, label: &str, attributes: Attributes<'_>, secret: &[u8]) -> Result<(), Box<dyn Error>>;
+    /// Secret of the first item matching `attributes`, unlocked items first.
+    fn find(&self, attributes: Attributes<'_>) -> Result<Option<Vec<u8>>, Box<dyn Error>>;
+    /// Deletes every item matching `attributes`.
+    fn delete_all(&self, attributes: Attributes<'_>) -> Result<(), Box<dyn Error>>;
+}
+
+impl Keyring for SecretService<'_> {
+    fn replace(&self, label: &str, attributes: Attributes<'_>, secret: &[u8]) -> Result<(), Box<dyn Error>> {
+        collection(self)?.create_item(label, attributes, secret, true, "text/plain")?;
+        Ok(())
+    }
+
+    fn find(&self, attributes: Attributes<'_>) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        let found = self.search_items(attributes)?;
+        let Some(item) = found.unlocked.first().or(found.locked.first()) else {
+            return Ok(None);
+        };
+        item.ensure_unlocked()?;
+        Ok(Some(item.get_secret()?))
+    }
+
+    fn delete_all(&self, attributes: Attributes<'_>) -> Result<(), Box<dyn Error>> {
+        let found = self.search_items(attributes)?;
+        for item in found.unlocked.iter().chain(found.locked.iter()) {
+            item.ensure_unlocked()?;
+            item.delete()?;
+        }
+        Ok(())
+    }
+}
+
+fn attributes(account: &str) -> Attributes<'_> {
+    HashMap::from([("service", SERVICE_NAME), ("account", account)])
+}
+
+fn connect() -> Result<SecretService<'static>, Box<dyn Error>> {
+    SecretService::connect(EncryptionType::Dh).map_err(|e| {
+        // No session bus, or nothing on it owns org.freedesktop.secrets
+        if matches!(e, SsError::Unavailable) || e.to_string().contains("ServiceUnknown") {
+            "No Secret Service provider is running. Install gnome-keyring or KeePassXC, \
+             or enable the encrypted file store in config.json."
+                .into()
+        } else {
+            format!("Failed to connect to the Secret Service: {}", e).into()
+        }
+    })
+}
+
+// The "default" alias is missing on some fresh keyrings; any collection will do
+fn collection<'a>(ss: &'a SecretService<'a>) -> Result<Collection<'a>, Box<dyn Error>> {
+    let collection = match ss.get_default_collection() {
+        Ok(collection) => collection,
+        Err(SsError::NoResult) => ss.get_any_collection()?,
+        Err(e) => return Err(e.into()),
+    };
+    // Prompts the user when the keyring is locked
+    collection.ensure_unlocked()?;
+    Ok(collection)
+}
+
+pub fn set(account: &str, secret: &str) -> Result<(), Box<dyn Error>> {
+    set_in(&connect()?, account, secret)
+}
+
+pub fn get(account: &str) -> Result<Option<String>, Box<dyn Error>> {
+    get_in(&connect()?, account)
+}
+
+pub fn delete(account: &str) -> Result<(), Box<dyn Error>> {
+    delete_in(&connect()?, account)
+}
+
+fn set_in(keyring: &impl Keyring, account: &str, secret: &str) -> Result<(), Box<dyn Error>> {
+    keyring.replace(&format!("Rephraser ({})", account), attributes(account), secret.as_bytes())
+}
+
+fn get_in(keyring: &impl Keyring, account: &str) -> Result<Option<String>, Box<dyn Error>> {
+    let Some(secret) = keyring.find(attributes(account))? else {
+        return Ok(None);
+    };
+    let value = String::from_utf8(secret)?;
+    if value.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(value))
+    }
+}
+
+fn delete_in(keyring: &impl Keyring, account: &str) -> Result<(), Box<dyn Error>> {
+    keyring.delete_all(attributes(account))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    // Label, attributes and secret of one item
+    type Item = (String, HashMap<String, String>, Vec<u8>);
+
+    // Behaves like a single unlocked collection
+    #[derive(Default)]
+    struct FakeKeyring {
+        items: RefCell<Vec<Item>>,
+    }
+
+    fn owned(attributes: &Attributes<'_>) -> HashMap<String, String> {
+        attributes.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    impl Keyring for FakeKeyring {
+        fn replace(&self, label: &str, attributes: Attributes<'_>, secret: &[u8]) -> Result<(), Box<dyn Error>> {
+            let attributes = owned(&attributes);
+            let mut items = self.items.borrow_mut();
+            items.retain(|(_, existing, _)| *existing != attributes);
+            items.push((label.to_string(), attributes, secret.to_vec()));
+            Ok(())
+        }
+
+        fn find(&self, attributes: Attributes<'_>) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+            let attributes = owned(&attributes);
+            Ok(self
+                .items
+                .borrow()
+                .iter()
+                .find(|(_, existing, _)| *existing == attributes)
+                .map(|(_, _, secret)| secret.clone()))
+        }
+
+        fn delete_all(&self, attributes: Attributes<'_>) -> Result<(), Box<dyn Error>> {
+            let attributes = owned(&attributes);
+            self.items.borrow_mut().retain(|(_, existing, _)| *existing != attributes);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn stores_one_labelled_item_per_account() {
+        let keyring = FakeKeyring::default();
+        set_in(&keyring, "api_key.openai", "sk-first").unwrap();
+        set_in(&keyring, "api_key.openai", "sk-second").unwrap();
+        set_in(&keyring, "api_key.claude", "sk-ant").unwrap();
+
+        let items = keyring.items.borrow();
+        assert_eq!(items.len(), 2);
+        let (label, attributes, secret) = &items[0];
+        assert_eq!(label, "Rephraser (api_key.openai)");
+        assert_eq!(attributes["service"], SERVICE_NAME);
+        assert_eq!(attributes["account"], "api_key.openai");
+        assert_eq!(secret, b"sk-second");
+        let (label, attributes, _) = &items[1];
+        assert_eq!(label, "Rephraser (api_key.claude)");
+        assert_eq!(attributes["service"], SERVICE_NAME);
+        assert_eq!(attributes["account"], "api_key.claude");
+    }
+
+    #[test]
+    fn round_trip_and_delete() {
+        let keyring = FakeKeyring::default();
+        assert_eq!(get_in(&keyring, "api_key.openai").unwrap(), None);
+
+        set_in(&keyring, "api_key.openai", "sk-test").unwrap();
+        assert_eq!(get_in(&keyring, "api_key.openai").unwrap().as_deref(), Some("sk-test"));
+        assert_eq!(get_in(&keyring, "api_key.claude").unwrap(), None);
+
+        delete_in(&keyring, "api_key.openai").unwrap();
+        assert_eq!(get_in(&keyring, "api_key.openai").unwrap(), None);
+        delete_in(&keyring, "api_key.openai").unwrap();
+    }
+
+    #[test]
+    fn empty_secrets_read_as_missing() {
+        let keyring = FakeKeyring::default();
+        set_in(&keyring, "api_key.openai", "").unwrap();
+        assert_eq!(get_in(&keyring, "api_key.openai").unwrap(), None);
+    }
+
+    #[test]
+    fn secrets_that_are_not_utf8_are_an_error() {
+        let keyring = FakeKeyring::default();
+        keyring.replace("Rephraser (api_key.openai)", attributes("api_key.openai"), &[0xff, 0xfe]).unwrap();
+        assert!(get_in(&keyring, "api_key.openai").is_err());
+    }
+
+    // Needs a session bus with a running, unlocked Secret Service provider
+    #[test]
+    #[ignore]
+    fn round_trip_through_the_session_keyring() {
+        let account = format!("api_key.test-{}", std::process::id());
+        set(&account, "sk-test").unwrap();
+        assert_eq!(get(&account).unwrap().as_deref(), Some("sk-test"));
+        delete(&account).unwrap();
+        assert_eq!(get(&account).unwrap(), None);
+    }
+}
@@ -1,11 +1,19 @@
 // Core Tauri application entry point
-use tauri::{AppHandle, Manager, Window};
+use tauri::{AppHandle, Emitter, Manager, State, Window};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
 
 mod ai;
 mod config;
+mod http;
 mod accessibility;
 mod keychain;
+mod requests;
+mod rich_text;
+mod styles;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SelectionInfo {
@@ -19,66 +27,212 @@ pub struct Position {
     pub y: f64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "lowercase")]
-pub enum Style {
-    Professional,
-    Casual,
-    Sarcasm,
+/// Selection handed to the popup. Rich selections arrive as Markdown.
+#[derive(Debug, Clone, Serialize)]
+pub struct SelectedText {
+    pub text: String,
+    pub rich: bool,
+}
+
+/// Payload of the `rephrase-chunk` event emitted while a response streams in.
+#[derive(Debug, Clone, Serialize)]
+pub struct RephraseChunk {
+    pub request_id: String,
+    pub delta: String,
+}
+
+/// Payload of the `rephrase-step` event emitted as each pipeline step finishes.
+#[derive(Debug, Clone, Serialize)]
+pub struct RephraseStep {
+    pub request_id: String,
+    #[serde(flatten)]
+    pub step: ai::StepOutput,
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn rephrase_text(
+    app: AppHandle,
+    registry: State<'_, ai::ProviderRegistry>,
+    requests: State<'_, requests::RequestRegistry>,
+    http: State<'_, http::HttpClient>,
+    styles: State<'_, styles::StyleRegistry>,
     text: String,
-    style: Style,
+    style: String,
     provider: String,
-    api_key: String,
-    custom_prompt: Option<String>,
-) -> Result<String, String> {
-    let prompt_ref = custom_prompt.as_deref().unwrap_or("");
-    eprintln!("🔄 Rephrase request: provider={}, style={:?}, custom={}, text_len={}", 
-        provider, style, !prompt_ref.is_empty(), text.len());
+    variables: Option<HashMap<String, String>>,
+    rich: Option<bool>,
+    request_id: Option<String>,
+) -> Result<ai::RephraseOutcome, ai::RephraseError> {
+    let rich = rich.unwrap_or(false);
+    eprintln!("🔄 Rephrase request: provider={}, style={}, rich={}, text_len={}", 
+        provider, style, rich, text.len());
     
-    const MAX_TEXT_LENGTH: usize = 10000;
-    if text.len() > MAX_TEXT_LENGTH {
-        return Err(format!("Text too long. Maximum {} characters allowed.", MAX_TEXT_LENGTH));
+    // No length check here: each provider enforces its own limit, and custom
+    // endpoints configure theirs
+    if text.trim().is_empty() {
+        return Err(ai::RephraseError::EmptyInput);
     }
     
-    if text.trim().is_empty() {
-        return Err("Text cannot be empty".to_string());
+    let style = styles.get(&style).ok_or(ai::RephraseError::UnknownStyle { style })?;
+    
+    // The popup sends what the user typed plus its local date; the app fills in the rest
+    let mut variables = variables.unwrap_or_default();
+    if !variables.contains_key("app_name") {
+        if let Some(app_name) = accessibility::source_app_name() {
+            variables.insert("app_name".to_string(), app_name);
+        }
     }
+    variables.entry("date".to_string()).or_insert_with(styles::today);
+    
+    // A pipeline's steps name styles by id; steps without a provider use the popup's.
+    // Nested pipelines are rejected when saving, and only a step's own prompt is used.
+    let pipeline = style
+        .steps
+        .iter()
+        .map(|step| {
+            let step_style = styles
+                .get(&step.style)
+                .ok_or_else(|| ai::RephraseError::UnknownStyle { style: step.style.clone() })?;
+            let step_provider = step.provider.clone().filter(|p| !p.trim().is_empty()).unwrap_or_else(|| provider.clone());
+            Ok((step_style, step_provider, step.model.clone().filter(|m| !m.trim().is_empty())))
+        })
+        .collect::<Result<Vec<_>, ai::RephraseError>>()?;
+    let steps: Vec<ai::Step> = pipeline
+        .iter()
+        .map(|(style, provider, model)| ai::Step { style, provider, model: model.as_deref() })
+        .collect();
+    
+    let emit_step = request_id.clone().map(|request_id| {
+        let app = app.clone();
+        move |step: &ai::StepOutput| {
+            let event = RephraseStep {
+                request_id: request_id.clone(),
+                step: step.clone(),
+            };
+            if let Err(e) = app.emit("rephrase-step", event) {
+                eprintln!("⚠️  Failed to emit pipeline step: {}", e);
+            }
+        }
+    });
+    let on_step = emit_step.as_ref().map(|f| f as &ai::StepSink<'_>);
+    
+    // When the popup supplies a request id, stream deltas to it as events;
+    // the command still resolves with the cleaned final text.
+    let emit_chunk = request_id.clone().map(|request_id| {
+        move |delta: &str| {
+            let chunk = RephraseChunk {
+                request_id: request_id.clone(),
+                delta: delta.to_string(),
+            };
+            if let Err(e) = app.emit("rephrase-chunk", chunk) {
+                eprintln!("⚠️  Failed to emit rephrase chunk: {}", e);
+            }
+        }
+    });
+    let on_chunk = emit_chunk.as_ref().map(|f| f as &ai::ChunkSink<'_>);
+    
+    // Every provider in the fallback chain reads its own key from the keychain
+    let api_key_for = |id: &str| -> Pin<Box<dyn Future<Output = Option<String>> + Send>> {
+        let id = id.to_string();
+        Box::pin(async move {
+            let account = keychain::api_key_account(&id);
+            match read_secret(account).await {
+                Ok(key) => key,
+                Err(e) => {
+                    eprintln!("⚠️  Failed to read API key for {}: {}", id, e);
+                    None
+                }
+            }
+        })
+    };
+    
+    let request_id = request_id.unwrap_or_else(|| requests.generate_id());
+    let cancelled = requests.begin(&request_id);
+    
+    eprintln!("✅ Calling AI module with provider: {}, streaming={}, request_id={}", provider, on_chunk.is_some(), request_id);
+    let client = http.get();
+    let rephrase = async {
+        if steps.is_empty() {
+            ai::rephrase_text(&registry, &client, &text, &style, &variables, &provider, &api_key_for, rich, on_chunk).await
+        } else {
+            ai::rephrase_pipeline(&registry, &client, &text, &steps, &variables, &api_key_for, rich, on_chunk, on_step).await
+        }
+    };
+    let result = tokio::select! {
+        result = rephrase => result,
+        _ = cancelled => {
+            eprintln!("🛑 Rephrase cancelled: {}", request_id);
+            return Err(ai::RephraseError::Cancelled);
+        }
+    };
     
-    if provider != "proxy" && api_key.trim().is_empty() {
-        eprintln!("❌ API key required for provider: {}", provider);
-        return Err("API key is required for custom providers. Please configure it in Settings or use the default (Proxy Server).".to_string());
+    // A newer request or hide_popup may have cancelled this one just as it finished
+    if !requests.finish(&request_id) {
+        eprintln!("🛑 Discarding late result for cancelled request: {}", request_id);
+        return Err(ai::RephraseError::Cancelled);
     }
     
-    eprintln!("✅ Calling AI module with provider: {}", provider);
-    match ai::rephrase_text(&text, &style, &provider, &api_key, prompt_ref).await {
+    match result {
         Ok(result) => {
-            eprintln!("✅ Rephrase successful, result_len={}", result.len());
+            eprintln!("✅ Rephrase successful via {}, result_len={}", result.provider, result.text.len());
             Ok(result)
         }
         Err(e) => {
             eprintln!("❌ Rephrase failed: {}", e);
-            Err(e.to_string())
+            Err(e)
         }
     }
 }
 
 #[tauri::command]
-async fn get_clipboard_text(app: AppHandle) -> Result<String, String> {
-    use tauri_plugin_clipboard_manager::ClipboardExt;
-    
-    app.clipboard()
-        .read_text()
-        .map_err(|e| e.to_string())
+fn list_styles(styles: State<'_, styles::StyleRegistry>) -> Vec<styles::Style> {
+    styles.list()
+}
+
+/// Checks a custom style's template and variables before Settings accepts the edit.
+#[tauri::command]
+fn validate_style(style: config::CustomStyle) -> Result<(), String> {
+    styles::validate(&style).map(drop).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn list_providers(registry: State<'_, ai::ProviderRegistry>) -> Vec<ai::ProviderInfo> {
+    registry.list()
+}
+
+#[tauri::command]
+async fn get_clipboard_text() -> Result<String, String> {
+    desktop_task(accessibility::read_clipboard_text)
+        .await
+        .map(Option::unwrap_or_default)
 }
 
 #[tauri::command]
-async fn get_selected_text() -> Result<String, String> {
-    let text = accessibility::get_selected_text()
-        .map_err(|e| e.to_string())?;
+async fn get_selected_text(
+    rich_selection: State<'_, rich_text::RichSelection>,
+) -> Result<SelectedText, String> {
+    // Before the popup steals focus, so replace_selection knows where to paste
+    accessibility::remember_source_window();
+    let captured = desktop_task(accessibility::get_selected_text).await?;
+    
+    // Formatting the model can keep travels as Markdown; anything else stays plain
+    let converted = captured
+        .html
+        .as_deref()
+        .map(rich_text::html_to_markdown)
+        .filter(|converted| converted.formatted && !converted.markdown.trim().is_empty());
+    let (text, rich) = match converted {
+        Some(converted) => {
+            eprintln!("📝 Rich selection: {} link(s)", converted.links.len());
+            rich_selection.remember(converted.links);
+            (converted.markdown, true)
+        }
+        None => {
+            rich_selection.remember(Vec::new());
+            (captured.text, false)
+        }
+    };
     
     // Trim whitespace and validate
     let trimmed = text.trim();
@@ -86,32 +240,67 @@ async fn get_selected_text() -> Result<String, String> {
         return Err("No text selected".to_string());
     }
     
-    Ok(trimmed.to_string())
+    Ok(SelectedText { text: trimmed.to_string(), rich })
 }
 
 #[tauri::command]
-async fn set_clipboard_text(app: AppHandle, text: String) -> Result<(), String> {
-    use tauri_plugin_clipboard_manager::ClipboardExt;
-    
-    app.clipboard()
-        .write_text(text)
-        .map_err(|e| e.to_string())
+async fn set_clipboard_text(text: String) -> Result<(), String> {
+    desktop_task(move || accessibility::write_clipboard_text(&text)).await
 }
 
 #[tauri::command]
 async fn copy_to_clipboard(
-    app: AppHandle,
+    rich_selection: State<'_, rich_text::RichSelection>,
     text: String,
+    rich: Option<bool>,
 ) -> Result<(), String> {
-    use tauri_plugin_clipboard_manager::ClipboardExt;
-    
     if text.is_empty() {
         return Err("Cannot copy empty text".to_string());
     }
     
-    app.clipboard()
-        .write_text(text)
-        .map_err(|e| format!("Failed to copy to clipboard: {}", e))
+    let html = rich.unwrap_or(false).then(|| rich_selection.render(&text));
+    desktop_task(move || match html {
+        Some((plain, html)) => accessibility::write_clipboard_rich(&plain, &html),
+        None => accessibility::write_clipboard_text(&text),
+    })
+    .await
+    .map_err(|e| format!("Failed to copy to clipboard: {}", e))
+}
+
+#[tauri::command]
+async fn replace_selection(
+    window: Window,
+    requests: State<'_, requests::RequestRegistry>,
+    rich_selection: State<'_, rich_text::RichSelection>,
+    text: String,
+    rich: Option<bool>,
+) -> Result<(), String> {
+    if text.is_empty() {
+        return Err("Cannot paste empty text".to_string());
+    }
+
+    // The popup has to be out of the way before focus can go back to the source app
+    requests.cancel_all();
+    window.hide().map_err(|e| e.to_string())?;
+
+    let html = rich.unwrap_or(false).then(|| rich_selection.render(&text));
+    desktop_task(move || match html {
+        Some((plain, html)) => accessibility::replace_selection(&plain, Some(&html)),
+        None => accessibility::replace_selection(&text, None),
+    })
+    .await
+    .map_err(|e| format!("Failed to replace selection: {}", e))
+}
+
+// Capture and paste poll the clipboard between thread sleeps for up to a few
+// seconds, and clipboard writes may wait for the compositor, so they run on
+// the blocking pool instead of stalling a runtime worker
+async fn desktop_task<T: Send + 'static>(
+    task: impl FnOnce() -> Result<T, Box<dyn std::error::Error>> + Send + 'static,
+) -> Result<T, String> {
+    tauri::async_runtime::spawn_blocking(move || task().map_err(|e| e.to_string()))
+        .await
+        .map_err(|e| e.to_string())?
 }
 
 #[tauri::command]
@@ -265,7 +454,19 @@ async fn show_popup_at_cursor(window: Window) -> Result<(), String> {
 
 
 #[tauri::command]
-async fn hide_popup(window: Window) -> Result<(), String> {
+fn cancel_rephrase(requests: State<'_, requests::RequestRegistry>, request_id: String) -> bool {
+    requests.cancel(&request_id)
+}
+
+#[tauri::command]
+async fn hide_popup(
+    window: Window,
+    requests: State<'_, requests::RequestRegistry>,
+) -> Result<(), String> {
+    let cancelled = requests.cancel_all();
+    if cancelled > 0 {
+        eprintln!("🛑 Cancelled {} in-flight request(s) on hide", cancelled);
+    }
     window.hide().map_err(|e| e.to_string())
 }
 
@@ -275,29 +476,201 @@ fn load_config() -> Result<config::AppConfig, String> {
 }
 
 #[tauri::command]
-fn save_config(config: config::AppConfig) -> Result<(), String> {
-    config::save(&config).map_err(|e| e.to_string())
+fn save_config(
+    registry: State<'_, ai::ProviderRegistry>,
+    styles: State<'_, styles::StyleRegistry>,
+    http: State<'_, http::HttpClient>,
+    config: config::AppConfig,
+) -> Result<(), String> {
+    // Reject unusable network settings and broken style templates before they reach disk
+    styles::validate_config(&config).map_err(|e| e.to_string())?;
+    http.configure(&config.network)?;
+    config::save(&config).map_err(|e| e.to_string())?;
+    registry.apply_config(&config);
+    styles.apply_config(&config);
+    keychain::use_store(config.credential_store);
+    Ok(())
 }
 
-#[tauri::command]
-fn get_api_key() -> Result<Option<String>, String> {
-    keychain::get("api_key").map_err(|e| format!("Failed to read API key from keychain: {}", e))
+// Pushes a freshly loaded config into the managed provider and HTTP state
+fn apply_config(app: &AppHandle, cfg: &config::AppConfig) {
+    keychain::use_store(cfg.credential_store);
+    app.state::<ai::ProviderRegistry>().apply_config(cfg);
+    app.state::<styles::StyleRegistry>().apply_config(cfg);
+    if let Err(e) = app.state::<http::HttpClient>().configure(&cfg.network) {
+        eprintln!("⚠️  Keeping default HTTP client: {}", e);
+    }
+}
+
+/// Whether the encrypted file store is in use, has been created, and still
+/// needs its passphrase.
+#[derive(Debug, Clone, Serialize)]
+struct CredentialStoreStatus {
+    encrypted_file: bool,
+    exists: bool,
+    unlocked: bool,
 }
 
 #[tauri::command]
-fn set_api_key(key: String) -> Result<(), String> {
-    if key.trim().is_empty() {
-        return keychain::delete("api_key")
-            .map_err(|e| format!("Failed to remove API key: {}", e));
+fn credential_store_status() -> CredentialStoreStatus {
+    CredentialStoreStatus {
+        encrypted_file: keychain::file_store::is_enabled(),
+        exists: keychain::file_store::exists().unwrap_or(false),
+        unlocked: keychain::file_store::is_unlocked(),
     }
-    keychain::set("api_key", key.trim())
-        .map_err(|e| format!("Failed to save API key to keychain: {}", e))
+}
+
+// Both derive the key with Argon2, which takes a moment
+#[tauri::command]
+async fn create_credential_store(passphrase: String, confirmation: String) -> Result<(), String> {
+    keychain_task(move || keychain::file_store::create(&passphrase, &confirmation).map_err(|e| e.to_string())).await
+}
+
+#[tauri::command]
+async fn unlock_credential_store(passphrase: String) -> Result<(), String> {
+    keychain_task(move || keychain::file_store::unlock(&passphrase).map_err(|e| e.to_string())).await
+}
+
+// Resolves aliases so `anthropic` and `claude` share one keychain account
+fn api_key_account(registry: &ai::ProviderRegistry, provider: &str) -> Result<String, String> {
+    registry
+        .get(provider)
+        .map(|p| keychain::api_key_account(p.id()))
+        .ok_or_else(|| format!("Unsupported provider: {}", provider))
+}
+
+/// All the webview ever learns about a stored key: whether it exists and how it ends.
+#[derive(Debug, Clone, Serialize)]
+struct ApiKeyStatus {
+    provider: String,
+    present: bool,
+    last_four: Option<String>,
+}
+
+// Keychain calls block (a D-Bus round trip to the Secret Service on Linux,
+// maybe an unlock prompt), so commands run them on the blocking pool instead
+// of the main thread or a runtime worker
+async fn keychain_task<T: Send + 'static>(
+    task: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    tauri::async_runtime::spawn_blocking(task)
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+async fn read_secret(account: String) -> Result<Option<String>, String> {
+    keychain_task(move || keychain::get(&account).map_err(|e| e.to_string())).await
+}
+
+fn api_key_status_for(provider_id: &str) -> Result<ApiKeyStatus, String> {
+    let key = keychain::get(&keychain::api_key_account(provider_id))
+        .map_err(|e| format!("Failed to read API key from keychain: {}", e))?;
+    // Short keys would be mostly given away by their last four characters
+    let last_four = key.as_deref().filter(|k| k.chars().count() >= 12).map(|k| {
+        let skip = k.chars().count() - 4;
+        k.chars().skip(skip).collect()
+    });
+    Ok(ApiKeyStatus {
+        provider: provider_id.to_string(),
+        present: key.is_some(),
+        last_four,
+    })
 }
 
 #[tauri::command]
-fn delete_api_key() -> Result<(), String> {
-    keychain::delete("api_key")
-        .map_err(|e| format!("Failed to remove API key: {}", e))
+async fn api_key_status(registry: State<'_, ai::ProviderRegistry>, provider: String) -> Result<ApiKeyStatus, String> {
+    let id = registry
+        .get(&provider)
+        .ok_or_else(|| format!("Unsupported provider: {}", provider))?
+        .id()
+        .to_string();
+    keychain_task(move || api_key_status_for(&id)).await
+}
+
+#[tauri::command]
+async fn set_api_key(registry: State<'_, ai::ProviderRegistry>, provider: String, key: String) -> Result<(), String> {
+    let account = api_key_account(&registry, &provider)?;
+    keychain_task(move || {
+        if key.trim().is_empty() {
+            return keychain::delete(&account)
+                .map_err(|e| format!("Failed to remove API key: {}", e));
+        }
+        keychain::set(&account, key.trim())
+            .map_err(|e| format!("Failed to save API key to keychain: {}", e))
+    })
+    .await
+}
+
+#[tauri::command]
+async fn delete_api_key(registry: State<'_, ai::ProviderRegistry>, provider: String) -> Result<(), String> {
+    let account = api_key_account(&registry, &provider)?;
+    keychain_task(move || keychain::delete(&account).map_err(|e| format!("Failed to remove API key: {}", e))).await
+}
+
+#[tauri::command]
+async fn list_api_keys(registry: State<'_, ai::ProviderRegistry>) -> Result<Vec<ApiKeyStatus>, String> {
+    let ids: Vec<String> = registry.list().into_iter().map(|provider| provider.id).collect();
+    keychain_task(move || ids.iter().map(|id| api_key_status_for(id)).collect()).await
+}
+
+/// Hands a stored key to the webview, but only after the user confirms in a
+/// native dialog that script running in the webview cannot click through.
+#[tauri::command]
+async fn reveal_api_key(
+    app: AppHandle,
+    registry: State<'_, ai::ProviderRegistry>,
+    provider: String,
+) -> Result<String, String> {
+    let backend = registry
+        .get(&provider)
+        .ok_or_else(|| format!("Unsupported provider: {}", provider))?;
+
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    app.dialog()
+        .message(format!(
+            "Show your saved {} API key in Settings? Anyone who can see your screen will be able to read it.",
+            backend.display_name()
+        ))
+        .title("Reveal API Key")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom("Reveal".to_string(), "Cancel".to_string()))
+        .show(move |confirmed| {
+            let _ = tx.send(confirmed);
+        });
+
+    if !rx.await.unwrap_or(false) {
+        return Err("Reveal cancelled".to_string());
+    }
+
+    eprintln!("🔑 API key revealed for provider: {}", backend.id());
+    read_secret(keychain::api_key_account(backend.id()))
+        .await
+        .map_err(|e| format!("Failed to read API key from keychain: {}", e))?
+        .ok_or_else(|| "No API key saved for this provider".to_string())
+}
+
+// Moves the old shared "api_key" slot to the account of the provider it was
+// used with, so existing users keep working after the per-provider split.
+fn migrate_legacy_api_key(registry: &ai::ProviderRegistry, provider: &str) {
+    let Some(backend) = registry.get(provider).filter(|p| p.id() != "proxy") else {
+        return;
+    };
+    let legacy = match keychain::get(keychain::LEGACY_API_KEY_ACCOUNT) {
+        Ok(Some(key)) => key,
+        _ => return,
+    };
+
+    let account = keychain::api_key_account(backend.id());
+    let result = match keychain::get(&account) {
+        Ok(Some(_)) => Ok(()),
+        _ => keychain::set(&account, &legacy),
+    }
+    .and_then(|()| keychain::delete(keychain::LEGACY_API_KEY_ACCOUNT));
+
+    match result {
+        Ok(()) => println!("✅ API key moved to the {} keychain entry", backend.id()),
+        Err(e) => eprintln!("⚠️  Failed to migrate legacy API key: {}", e),
+    }
 }
 
 #[tauri::command]
@@ -332,19 +705,35 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_clipboard_manager::init())
         .plugin(tauri_plugin_global_shortcut::Builder::new().build())
+        .plugin(tauri_plugin_dialog::init())
+        .manage(ai::ProviderRegistry::with_builtin_providers())
+        .manage(requests::RequestRegistry::default())
+        .manage(http::HttpClient::default())
+        .manage(rich_text::RichSelection::default())
+        .manage(styles::StyleRegistry::default())
         .invoke_handler(tauri::generate_handler![
             rephrase_text,
+            list_styles,
+            validate_style,
+            list_providers,
+            cancel_rephrase,
             get_clipboard_text,
             get_selected_text,
             set_clipboard_text,
             copy_to_clipboard,
+            replace_selection,
             show_popup_at_cursor,
             hide_popup,
             load_config,
             save_config,
-            get_api_key,
+            api_key_status,
             set_api_key,
             delete_api_key,
+            list_api_keys,
+            reveal_api_key,
+            credential_store_status,
+            create_credential_store,
+            unlock_credential_store,
             check_accessibility,
         ])
         .setup(|app| {
@@ -378,12 +767,13 @@ pub fn run() {
             match config::load() {
                 Ok(cfg) => {
                     println!("✅ Config loaded: provider={}", cfg.model_provider);
+                    apply_config(app.handle(), &cfg);
                     
                     // Migrate plaintext API key from config.json to keychain
                     if let Some(ref key) = cfg.api_key {
                         if !key.trim().is_empty() {
                             println!("🔐 Migrating API key from config.json to keychain...");
-                            match keychain::set("api_key", key.trim()) {
+                            match keychain::set(keychain::LEGACY_API_KEY_ACCOUNT, key.trim()) {
                                 Ok(()) => {
                                     // Re-save config to strip the api_key field from JSON
                                     let mut cleaned = cfg.clone();
@@ -400,6 +790,8 @@ pub fn run() {
                             }
                         }
                     }
+                    
+                    migrate_legacy_api_key(&app.state::<ai::ProviderRegistry>(), &cfg.model_provider);
                 }
                 Err(_) => {
                     let default_config = config::AppConfig::default();
@@ -415,6 +807,7 @@ pub fn run() {
                             if let Ok(content) = std::fs::read_to_string(&bundled_config) {
                                 if let Ok(bundled) = serde_json::from_str::<config::AppConfig>(&content) {
                                     let _ = config::save(&bundled);
+                                    apply_config(app.handle(), &bundled);
                                     println!("✅ Loaded bundled config for testing");
                                 }
                             }
@@ -0,0 +1,119 @@
+// In-flight rephrase tracking so abandoned or superseded requests never deliver results
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tokio::sync::oneshot;
+
+#[derive(Default)]
+pub struct RequestRegistry {
+    in_flight: Mutex<HashMap<String, oneshot::Sender<()>>>,
+    next_id: AtomicU64,
+}
+
+impl RequestRegistry {
+    /// Id for callers that did not supply one.
+    pub fn generate_id(&self) -> String {
+        format!("internal-{}", self.next_id.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Registers a request and cancels every request still in flight, since the
+    /// popup only ever shows the newest result. The returned receiver resolves
+    /// once the request is cancelled.
+    pub fn begin(&self, request_id: &str) -> oneshot::Receiver<()> {
+        let (tx, rx) = oneshot::channel();
+        let mut in_flight = self.in_flight.lock().unwrap();
+        for (id, _) in in_flight.drain() {
+            eprintln!("🛑 Superseding in-flight request: {}", id);
+        }
+        in_flight.insert(request_id.to_string(), tx);
+        rx
+    }
+
+    /// Removes a completed request. Returns `false` when it was cancelled or
+    /// superseded in the meantime and its result must be discarded.
+    pub fn finish(&self, request_id: &str) -> bool {
+        self.in_flight.lock().unwrap().remove(request_id).is_some()
+    }
+
+    pub fn cancel(&self, request_id: &str) -> bool {
+        match self.in_flight.lock().unwrap().remove(request_id) {
+            Some(tx) => {
+                let _ = tx.send(());
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn cancel_all(&self) -> usize {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        let count = in_flight.len();
+        for (_, tx) in in_flight.drain() {
+            let _ = tx.send(());
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::oneshot::error::TryRecvError;
+
+    // The rephrase task stops as soon as its receiver resolves, sent or dropped
+    fn resolved(rx: &mut oneshot::Receiver<()>) -> bool {
+        !matches!(rx.try_recv(), Err(TryRecvError::Empty))
+    }
+
+    #[test]
+    fn a_new_request_supersedes_the_previous_one() {
+        let registry = RequestRegistry::default();
+        let mut first = registry.begin("a");
+        let mut second = registry.begin("b");
+
+        assert!(resolved(&mut first));
+        assert!(!resolved(&mut second));
+        assert!(!registry.finish("a"), "a superseded result must be discarded");
+        assert!(registry.finish("b"));
+    }
+
+    #[test]
+    fn finish_only_removes_its_own_request() {
+        let registry = RequestRegistry::default();
+        let mut current = registry.begin("b");
+
+        assert!(!registry.finish("a"));
+        assert!(!resolved(&mut current));
+        assert!(registry.finish("b"));
+        assert!(!registry.finish("b"));
+    }
+
+    #[test]
+    fn cancel_resolves_only_that_request() {
+        let registry = RequestRegistry::default();
+        let mut rx = registry.begin("a");
+
+        assert!(!registry.cancel("other"));
+        assert!(!resolved(&mut rx));
+        assert!(registry.cancel("a"));
+        assert_eq!(rx.try_recv(), Ok(()));
+        assert!(!registry.finish("a"));
+    }
+
+    #[test]
+    fn cancel_all_resolves_everything_in_flight() {
+        let registry = RequestRegistry::default();
+        let mut rx = registry.begin("a");
+
+        assert_eq!(registry.cancel_all(), 1);
+        assert_eq!(rx.try_recv(), Ok(()));
+        assert!(!registry.finish("a"));
+        assert_eq!(registry.cancel_all(), 0);
+    }
+
+    #[test]
+    fn generated_ids_are_unique() {
+        let registry = RequestRegistry::default();
+        assert_ne!(registry.generate_id(), registry.generate_id());
+    }
+}
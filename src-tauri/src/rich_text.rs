@@ -0,0 +1,571 @@
+// Converts rich clipboard HTML to Markdown the model can rewrite, and back
+use ego_tree::NodeRef;
+use pulldown_cmark::{CowStr, Event, Options, Parser, Tag, TagEnd};
+use scraper::{Html, Node};
+use std::sync::Mutex;
+
+/// Prefix of the placeholders that stand in for link targets. Models tend to
+/// "fix" or drop long URLs, so they only ever see `LINK1`, `LINK2`...
+const LINK_PLACEHOLDER: &str = "LINK";
+
+// Stands in for <br> during conversion. It counts as whitespace, so trimming
+// a block drops trailing breaks, and becomes a Markdown hard break at the end.
+const LINE_BREAK: char = '\u{2028}';
+
+/// Markdown rendering of a rich selection.
+#[derive(Debug, Clone, Default)]
+pub struct RichText {
+    pub markdown: String,
+    /// Link targets, indexed by placeholder number minus one.
+    pub links: Vec<String>,
+    /// Whether the HTML carried any formatting Markdown can express.
+    pub formatted: bool,
+}
+
+/// Link targets of the last captured selection, which the rephrased Markdown
+/// refers to by placeholder.
+#[derive(Default)]
+pub struct RichSelection {
+    links: Mutex<Vec<String>>,
+}
+
+impl RichSelection {
+    pub fn remember(&self, links: Vec<String>) {
+        *self.links.lock().unwrap() = links;
+    }
+
+    /// Plain and HTML flavors of rephrased Markdown.
+    pub fn render(&self, markdown: &str) -> (String, String) {
+        let links = self.links.lock().unwrap();
+        (markdown_to_plain(markdown), markdown_to_html(markdown, &links))
+    }
+}
+
+/// Converts an HTML fragment to Markdown: bold, italics, strikethrough,
+/// code, links, headings, lists, quotes and rules. Anything else keeps its
+/// text and loses its styling.
+pub fn html_to_markdown(html: &str) -> RichText {
+    let document = Html::parse_fragment(html);
+    let mut converter = Converter::default();
+    let markdown = converter.children(document.tree.root());
+
+    RichText {
+        markdown: tidy(&hard_breaks(markdown.trim())),
+        links: converter.links,
+        formatted: converter.formatted,
+    }
+}
+
+/// Renders Markdown as HTML, swapping link placeholders for their targets.
+/// Raw HTML in the model's reply is escaped rather than passed through, and
+/// links or images pointing anywhere but a placeholder or an http(s) or
+/// mailto URL keep only their text.
+pub fn markdown_to_html(markdown: &str, links: &[String]) -> String {
+    // Whether each open link or image was kept, to drop its end tag with it
+    let mut kept = Vec::new();
+    let events = Parser::new_ext(markdown, Options::ENABLE_STRIKETHROUGH).filter_map(|event| match event {
+        Event::Start(Tag::Link { link_type, dest_url, title, id }) => {
+            let dest_url = resolve_link(dest_url, links);
+            kept.push(dest_url.is_some());
+            dest_url.map(|dest_url| Event::Start(Tag::Link { link_type, dest_url, title, id }))
+        }
+        Event::Start(Tag::Image { link_type, dest_url, title, id }) => {
+            let dest_url = resolve_link(dest_url, links);
+            kept.push(dest_url.is_some());
+            dest_url.map(|dest_url| Event::Start(Tag::Image { link_type, dest_url, title, id }))
+        }
+        Event::End(end @ (TagEnd::Link | TagEnd::Image)) => kept.pop().unwrap_or(false).then_some(Event::End(end)),
+        Event::Html(html) | Event::InlineHtml(html) => Some(Event::Text(html)),
+        event => Some(event),
+    });
+
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, events);
+    html
+}
+
+/// Renders Markdown as the plain text a rich paste would read as, for apps
+/// that only accept the plain flavor.
+pub fn markdown_to_plain(markdown: &str) -> String {
+    let mut text = String::new();
+    // Next number of each open list, None for bullets
+    let mut lists: Vec<Option<u64>> = Vec::new();
+
+    for event in Parser::new_ext(markdown, Options::ENABLE_STRIKETHROUGH) {
+        match event {
+            Event::Text(t) | Event::Code(t) | Event::Html(t) | Event::InlineHtml(t) => text.push_str(&t),
+            Event::SoftBreak | Event::HardBreak => text.push('\n'),
+            Event::Rule => text.push_str("\n\n"),
+            Event::Start(Tag::List(start)) => lists.push(start),
+            Event::End(TagEnd::List(_)) => {
+                lists.pop();
+                if lists.is_empty() {
+                    text.push('\n');
+                }
+            }
+            Event::Start(Tag::Item) => {
+                if !text.is_empty() && !text.ends_with('\n') {
+                    text.push('\n');
+                }
+                text.push_str(&"  ".repeat(lists.len().saturating_sub(1)));
+                match lists.last_mut() {
+                    Some(Some(number)) => {
+                        text.push_str(&format!("{}. ", number));
+                        *number += 1;
+                    }
+                    _ => text.push_str("• "),
+                }
+            }
+            // An item holding a nested list already ends with that list's newline
+            Event::End(TagEnd::Item) if !text.ends_with('\n') => text.push('\n'),
+            Event::End(TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::BlockQuote(_) | TagEnd::CodeBlock)
+                if lists.is_empty() =>
+            {
+                text.push_str("\n\n")
+            }
+            _ => {}
+        }
+    }
+    tidy(&text)
+}
+
+// The captured target of a placeholder, or the destination itself when it is
+// a web or mail address. Anything else (`javascript:`, `data:`, paths, unknown
+// placeholders) must not reach the clipboard.
+fn resolve_link<'a>(dest_url: CowStr<'a>, links: &[String]) -> Option<CowStr<'a>> {
+    let captured = dest_url
+        .strip_prefix(LINK_PLACEHOLDER)
+        .and_then(|number| number.parse::<usize>().ok())
+        .and_then(|number| links.get(number.checked_sub(1)?));
+    if let Some(url) = captured {
+        return Some(CowStr::from(url.clone()));
+    }
+
+    let lower = dest_url.trim().to_ascii_lowercase();
+    ["http://", "https://", "mailto:"]
+        .iter()
+        .any(|scheme| lower.starts_with(scheme))
+        .then_some(dest_url)
+}
+
+// Trims each line end and collapses runs of blank lines
+fn tidy(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut blank_lines = 0;
+    for line in text.trim().lines() {
+        let line = line.trim_end_matches([' ', '\t']);
+        if line.is_empty() {
+            blank_lines += 1;
+            if blank_lines > 1 {
+                continue;
+            }
+        } else {
+            blank_lines = 0;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.trim_end().to_string()
+}
+
+#[derive(Default)]
+struct Converter {
+    links: Vec<String>,
+    formatted: bool,
+}
+
+impl Converter {
+    fn children(&mut self, node: NodeRef<Node>) -> String {
+        let mut out = String::new();
+        for child in node.children() {
+            self.node(child, &mut out);
+        }
+        out
+    }
+
+    fn node(&mut self, node: NodeRef<Node>, out: &mut String) {
+        match node.value() {
+            Node::Text(text) => push_text(out, &escape(&collapse_whitespace(text))),
+            Node::Element(_) => self.element(node, out),
+            _ => {}
+        }
+    }
+
+    fn element(&mut self, node: NodeRef<Node>, out: &mut String) {
+        let Node::Element(element) = node.value() else {
+            return;
+        };
+        let style = element.attr("style").unwrap_or("").to_lowercase().replace(' ', "");
+
+        match element.name() {
+            "script" | "style" | "head" | "title" | "meta" | "template" | "noscript" | "img" => {}
+            "br" => out.push(LINE_BREAK),
+            "hr" => {
+                self.formatted = true;
+                push_block(out, "---");
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.formatted = true;
+                let level = element.name()[1..].parse::<usize>().unwrap_or(1);
+                let content = self.children(node).replace([LINE_BREAK, '\n'], " ");
+                if !content.trim().is_empty() {
+                    push_block(out, &format!("{} {}", "#".repeat(level), content.trim()));
+                }
+            }
+            "blockquote" => {
+                self.formatted = true;
+                let content = tidy(&hard_breaks(self.children(node).trim()));
+                let quoted = content
+                    .lines()
+                    .map(|line| if line.is_empty() { ">".to_string() } else { format!("> {}", line) })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                push_block(out, &quoted);
+            }
+            "pre" => {
+                self.formatted = true;
+                let code = node_text(node);
+                push_block(out, &format!("```\n{}\n```", code.trim_end_matches('\n')));
+            }
+            "ul" | "ol" => {
+                self.formatted = true;
+                let list = self.list(node, element.name() == "ol", element.attr("start"));
+                push_block(out, &list);
+            }
+            "code" | "kbd" | "samp" => {
+                self.formatted = true;
+                let code = collapse_whitespace(&node_text(node));
+                let fence = if code.contains('`') { "``" } else { "`" };
+                push_text(out, &format!("{fence}{code}{fence}"));
+            }
+            "a" => {
+                let content = self.children(node);
+                match element.attr("href").filter(|href| !href.is_empty() && !href.starts_with('#')) {
+                    Some(href) if !content.trim().is_empty() => {
+                        self.formatted = true;
+                        let placeholder = self.link_placeholder(href);
+                        wrap_inline(out, &content, "[", &format!("]({})", placeholder));
+                    }
+                    _ => push_text(out, &content),
+                }
+            }
+            "strong" | "b" if !is_normal_weight(&style) => self.inline(node, out, "**"),
+            "em" | "i" | "cite" | "dfn" => self.inline(node, out, "*"),
+            "s" | "del" | "strike" => self.inline(node, out, "~~"),
+            // Google Docs and Word style spans instead of using semantic tags
+            _ if is_bold(&style) => self.inline(node, out, "**"),
+            _ if style.contains("font-style:italic") => self.inline(node, out, "*"),
+            _ if style.contains("line-through") => self.inline(node, out, "~~"),
+            name if is_block(name) => {
+                let content = self.children(node);
+                push_block(out, &content);
+            }
+            _ => {
+                let content = self.children(node);
+                push_text(out, &content);
+            }
+        }
+    }
+
+    fn inline(&mut self, node: NodeRef<Node>, out: &mut String, marker: &str) {
+        let content = self.children(node);
+        if !content.trim().is_empty() {
+            self.formatted = true;
+        }
+        wrap_inline(out, &content, marker, marker);
+    }
+
+    fn list(&mut self, node: NodeRef<Node>, ordered: bool, start: Option<&str>) -> String {
+        let mut number = start.and_then(|start| start.trim().parse::<u64>().ok()).unwrap_or(1);
+        let mut items: Vec<String> = Vec::new();
+
+        for child in node.children() {
+            let Node::Element(element) = child.value() else {
+                continue;
+            };
+            // Nested lists sometimes sit directly in the parent list
+            if element.name() != "li" {
+                let content = tidy(&self.children_as_block(child));
+                if let (Some(last), false) = (items.last_mut(), content.is_empty()) {
+                    last.push('\n');
+                    last.push_str(&indent(&content, "  "));
+                }
+                continue;
+            }
+
+            let marker = if ordered {
+                let marker = format!("{}. ", number);
+                number += 1;
+                marker
+            } else {
+                "- ".to_string()
+            };
+            let content = tidy(&hard_breaks(self.children(child).trim())).replace("\n\n", "\n");
+            let padding = " ".repeat(marker.len());
+            items.push(format!("{}{}", marker, indent(&content, &padding).trim_start()));
+        }
+        items.join("\n")
+    }
+
+    fn children_as_block(&mut self, node: NodeRef<Node>) -> String {
+        let mut out = String::new();
+        self.node(node, &mut out);
+        out
+    }
+
+    fn link_placeholder(&mut self, href: &str) -> String {
+        let index = match self.links.iter().position(|link| link == href) {
+            Some(index) => index,
+            None => {
+                self.links.push(href.to_string());
+                self.links.len() - 1
+            }
+        };
+        format!("{}{}", LINK_PLACEHOLDER, index + 1)
+    }
+}
+
+fn is_block(name: &str) -> bool {
+    matches!(
+        name,
+        "p" | "div" | "section" | "article" | "header" | "footer" | "main" | "nav" | "aside"
+            | "address" | "figure" | "figcaption" | "li" | "dl" | "dt" | "dd" | "table" | "tr"
+            | "tbody" | "thead" | "tfoot" | "caption" | "body" | "html"
+    )
+}
+
+fn is_bold(style: &str) -> bool {
+    ["font-weight:bold", "font-weight:600", "font-weight:700", "font-weight:800", "font-weight:900"]
+        .iter()
+        .any(|weight| style.contains(weight))
+}
+
+// Google Docs wraps the whole copy in <b style="font-weight:normal">
+fn is_normal_weight(style: &str) -> bool {
+    style.contains("font-weight:normal") || style.contains("font-weight:400")
+}
+
+fn node_text(node: NodeRef<Node>) -> String {
+    node.descendants()
+        .filter_map(|node| match node.value() {
+            Node::Text(text) => Some(&**text),
+            _ => None,
+        })
+        .collect()
+}
+
+// HTML treats any run of whitespace, newlines included, as one space
+fn collapse_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() && c != '\u{a0}' {
+            if !in_space {
+                out.push(' ');
+            }
+            in_space = true;
+        } else {
+            out.push(c);
+            in_space = false;
+        }
+    }
+    out
+}
+
+// Escapes what would otherwise read as Markdown. Underscores inside words
+// never start emphasis, so snake_case stays readable.
+fn escape(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    for (i, &c) in chars.iter().enumerate() {
+        let escaped = match c {
+            '\\' | '*' | '`' | '[' | ']' | '<' => true,
+            '_' => {
+                let before = i.checked_sub(1).and_then(|i| chars.get(i));
+                let after = chars.get(i + 1);
+                !(before.is_some_and(|c| c.is_alphanumeric()) && after.is_some_and(|c| c.is_alphanumeric()))
+            }
+            _ => false,
+        };
+        if escaped {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+// Inline text never starts a line with a space or doubles one up. Leading
+// spaces of a whole fragment are left for the caller, who knows what precedes it.
+fn push_text(out: &mut String, text: &str) {
+    if out.ends_with(['\n', ' ', LINE_BREAK]) {
+        out.push_str(text.trim_start_matches(' '));
+    } else {
+        out.push_str(text);
+    }
+}
+
+fn hard_breaks(text: &str) -> String {
+    text.replace(LINE_BREAK, "\\\n")
+}
+
+// Blocks are separated by one blank line
+fn push_block(out: &mut String, block: &str) {
+    let block = block.trim();
+    if block.is_empty() {
+        return;
+    }
+    let kept = out.trim_end().len();
+    out.truncate(kept);
+    if !out.is_empty() {
+        out.push_str("\n\n");
+    }
+    out.push_str(block);
+    out.push_str("\n\n");
+}
+
+// Emphasis markers must hug the text, so surrounding spaces move outside them
+fn wrap_inline(out: &mut String, content: &str, open: &str, close: &str) {
+    let trimmed = content.trim();
+    if trimmed.is_empty() {
+        push_text(out, content);
+        return;
+    }
+    if content.starts_with(' ') {
+        push_text(out, " ");
+    }
+    push_text(out, &format!("{}{}{}", open, trimmed, close));
+    if content.ends_with(' ') {
+        out.push(' ');
+    }
+}
+
+fn indent(text: &str, padding: &str) -> String {
+    text.lines()
+        .map(|line| if line.is_empty() { String::new() } else { format!("{}{}", padding, line) })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn google_docs_wrapper_is_not_bold() {
+        let html = r#"<meta charset="utf-8"><b style="font-weight:normal;" id="docs-internal-guid-1"><p dir="ltr"><span style="font-weight:400;">Plain and </span><span style="font-weight:700;">bold</span><span style="font-weight:400;"> text</span></p></b>"#;
+        let rich = html_to_markdown(html);
+        assert_eq!(rich.markdown, "Plain and **bold** text");
+        assert!(rich.formatted);
+    }
+
+    #[test]
+    fn unformatted_html_is_reported_as_such() {
+        let rich = html_to_markdown("<p>Just   some\ntext</p>");
+        assert_eq!(rich.markdown, "Just some text");
+        assert!(!rich.formatted);
+    }
+
+    #[test]
+    fn nested_lists() {
+        let html = "<ul><li>One<ul><li>Inner</li></ul></li><li>Two</li></ul><ol start=\"3\"><li>Three</li><li>Four</li></ol>";
+        let rich = html_to_markdown(html);
+        assert_eq!(rich.markdown, "- One\n  - Inner\n- Two\n\n3. Three\n4. Four");
+
+        let plain = markdown_to_plain(&rich.markdown);
+        assert_eq!(plain, "• One\n  • Inner\n• Two\n\n3. Three\n4. Four");
+    }
+
+    #[test]
+    fn list_nested_directly_in_its_parent() {
+        let rich = html_to_markdown("<ol><li>First</li><ul><li>Sub</li></ul><li>Second</li></ol>");
+        assert_eq!(rich.markdown, "1. First\n  - Sub\n2. Second");
+    }
+
+    #[test]
+    fn links_travel_as_placeholders() {
+        let html = r#"<p>See <a href="https://example.com/a?b=1">the docs</a>, <a href="https://example.com/c">this</a> and <a href="https://example.com/a?b=1">again</a>.</p>"#;
+        let rich = html_to_markdown(html);
+        assert_eq!(rich.markdown, "See [the docs](LINK1), [this](LINK2) and [again](LINK1).");
+        assert_eq!(rich.links, vec!["https://example.com/a?b=1", "https://example.com/c"]);
+
+        let html = markdown_to_html("Read [the guide](LINK2) or [that](LINK1).", &rich.links);
+        assert_eq!(
+            html,
+            "<p>Read <a href=\"https://example.com/c\">the guide</a> or <a href=\"https://example.com/a?b=1\">that</a>.</p>\n"
+        );
+    }
+
+    #[test]
+    fn anchors_and_unknown_placeholders_lose_their_link() {
+        let rich = html_to_markdown(r##"<a href="#top">Top</a>"##);
+        assert_eq!(rich.markdown, "Top");
+        assert!(rich.links.is_empty());
+
+        let html = markdown_to_html("[gone](LINK3)", &["https://example.com".to_string()]);
+        assert_eq!(html, "<p>gone</p>\n");
+    }
+
+    #[test]
+    fn unsafe_link_targets_keep_only_their_text() {
+        let html = markdown_to_html(
+            "[a](javascript:alert(1)) [b](data:text/html,hi) [c](JavaScript:x) ![d](data:image/png;base64,AA) [e](/etc/passwd)",
+            &[],
+        );
+        assert_eq!(html, "<p>a b c d e</p>\n");
+
+        let html = markdown_to_html(
+            "[web](https://example.com) [plain](http://example.com) [mail](mailto:me@example.com) **[bold](LINK1)**",
+            &["https://example.com/doc".to_string()],
+        );
+        assert_eq!(
+            html,
+            "<p><a href=\"https://example.com\">web</a> <a href=\"http://example.com\">plain</a> \
+             <a href=\"mailto:me@example.com\">mail</a> <strong><a href=\"https://example.com/doc\">bold</a></strong></p>\n"
+        );
+    }
+
+    #[test]
+    fn selection_renders_with_remembered_links() {
+        let selection = RichSelection::default();
+        selection.remember(vec!["https://example.com".to_string()]);
+        let (plain, html) = selection.render("**Hi** [there](LINK1)");
+        assert_eq!(plain, "Hi there");
+        assert_eq!(html, "<p><strong>Hi</strong> <a href=\"https://example.com\">there</a></p>\n");
+    }
+
+    #[test]
+    fn markdown_characters_in_text_are_escaped() {
+        let rich = html_to_markdown("<p>2 * 3 = [six] `code` &lt;tag&gt; _under_ snake_case \\ path</p>");
+        assert_eq!(rich.markdown, r"2 \* 3 = \[six\] \`code\` \<tag> \_under\_ snake_case \\ path");
+        assert_eq!(markdown_to_plain(&rich.markdown), "2 * 3 = [six] `code` <tag> _under_ snake_case \\ path");
+    }
+
+    #[test]
+    fn raw_html_in_replies_is_escaped() {
+        let html = markdown_to_html("Hello <script>alert(1)</script>", &[]);
+        assert_eq!(html, "<p>Hello &lt;script&gt;alert(1)&lt;/script&gt;</p>\n");
+    }
+
+    #[test]
+    fn headings_quotes_code_and_breaks() {
+        let html = "<h2>Title</h2><blockquote><p>Quoted<br>line</p></blockquote><p>Use <code>cargo test</code></p><pre>fn main() {}\n</pre><hr><p><em>end</em> <s>old</s></p>";
+        let rich = html_to_markdown(html);
+        assert_eq!(
+            rich.markdown,
+            "## Title\n\n> Quoted\\\n> line\n\nUse `cargo test`\n\n```\nfn main() {}\n```\n\n---\n\n*end* ~~old~~"
+        );
+    }
+
+    #[test]
+    fn formatting_survives_a_round_trip() {
+        let html = r#"<p><strong>Bold</strong>, <em>italic</em> and <a href="https://example.com">a link</a></p><ul><li>one</li><li>two</li></ul>"#;
+        let rich = html_to_markdown(html);
+        let back = markdown_to_html(&rich.markdown, &rich.links);
+        assert_eq!(
+            back,
+            "<p><strong>Bold</strong>, <em>italic</em> and <a href=\"https://example.com\">a link</a></p>\n<ul>\n<li>one</li>\n<li>two</li>\n</ul>\n"
+        );
+    }
+}
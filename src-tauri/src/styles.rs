@@ -0,0 +1,275 @@
+// Rephrasing styles: the built-ins and the user's own, resolved by id
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::config::{AppConfig, CustomStyle, PipelineStep, StyleExample, StyleVariable};
+
+pub mod template;
+
+use template::{Template, TemplateError, BUILTIN_VARIABLES};
+
+/// Style used when a config names none.
+pub const DEFAULT_STYLE_ID: &str = "professional";
+
+// (id, name, prompt) of the styles that ship with the app
+const BUILTIN_STYLES: [(&str, &str, &str); 3] = [
+    (
+        "professional",
+        "Professional",
+        "Rephrase the following text in a professional, formal tone suitable for business communication. Maintain the core message but improve clarity and professionalism.",
+    ),
+    (
+        "casual",
+        "Casual",
+        "Rephrase the following text in a casual, friendly tone suitable for informal communication. Make it conversational and approachable.",
+    ),
+    (
+        "sarcasm",
+        "Sarcasm",
+        "Rephrase the following text with subtle sarcasm while maintaining the surface-level message. Keep it witty but not offensive.",
+    ),
+];
+
+// Built-in variables the user supplies rather than the app
+const INPUT_VARIABLES: [&str; 2] = ["language", "max_length"];
+
+/// A custom style that cannot be saved.
+#[derive(Debug, thiserror::Error)]
+pub enum StyleError {
+    #[error("Style \"{style}\": {error}")]
+    Template { style: String, error: TemplateError },
+    #[error("Style \"{style}\": step {step} uses \"{step_style}\", which does not exist")]
+    UnknownStep { style: String, step: usize, step_style: String },
+    #[error("Style \"{style}\": step {step} uses \"{step_style}\", which is a pipeline itself")]
+    NestedPipeline { style: String, step: usize, step_style: String },
+}
+
+/// One way of rephrasing. Built-in and user styles are the same kind of
+/// object; a user style with a built-in id replaces the built-in.
+#[derive(Debug, Clone, Serialize)]
+pub struct Style {
+    pub id: String,
+    pub name: String,
+    /// Template for what the model is asked to do with the text.
+    pub prompt: String,
+    /// The id ships with the app.
+    pub builtin: bool,
+    /// A built-in whose name or prompt the user replaced.
+    pub overridden: bool,
+    /// Values the popup asks for: `language` and `max_length` when the prompt
+    /// uses them, then the style's own variables.
+    pub variables: Vec<StyleVariable>,
+    /// Sent as earlier user/assistant turns so the model sees the voice.
+    pub examples: Vec<StyleExample>,
+    /// Styles run in order instead of this one's prompt, when not empty.
+    pub steps: Vec<PipelineStep>,
+    #[serde(skip)]
+    template: Template,
+}
+
+impl Style {
+    /// Still the shipped definition, so services with their own copy of the
+    /// built-in prompts (the proxy) can refer to it by id.
+    pub fn is_stock(&self) -> bool {
+        self.builtin && !self.overridden
+    }
+
+    pub fn is_pipeline(&self) -> bool {
+        !self.steps.is_empty()
+    }
+
+    /// The prompt places the text itself instead of having it appended.
+    pub fn uses_text(&self) -> bool {
+        self.template.uses("text")
+    }
+
+    /// The prompt with `text` and `values` filled in. Variables left empty
+    /// fall back to their declared default.
+    pub fn render(&self, text: &str, values: &HashMap<String, String>) -> String {
+        let mut all: HashMap<String, String> = self
+            .variables
+            .iter()
+            .map(|variable| (variable.name.clone(), variable.default.clone()))
+            .collect();
+        all.extend(
+            values
+                .iter()
+                .filter(|(_, value)| !value.trim().is_empty())
+                .map(|(name, value)| (name.clone(), value.clone())),
+        );
+        all.insert("text".to_string(), text.to_string());
+        self.template.render(&all)
+    }
+}
+
+/// Every style the popup can use, in display order: built-ins first.
+pub struct StyleRegistry {
+    styles: RwLock<Vec<Style>>,
+}
+
+impl StyleRegistry {
+    pub fn with_builtin_styles() -> Self {
+        Self {
+            styles: RwLock::new(builtin_styles()),
+        }
+    }
+
+    /// Rebuilds the registry from the built-ins and `config.custom_styles`.
+    pub fn apply_config(&self, config: &AppConfig) {
+        let mut styles = builtin_styles();
+        for custom in &config.custom_styles {
+            // Only hand-edited configs get here with a broken template
+            let template = validate(custom).unwrap_or_else(|e| {
+                eprintln!("⚠️  {}; using its prompt as plain text", e);
+                Template::literal(&custom.prompt)
+            });
+            let variables = popup_variables(&template, &custom.variables);
+            match styles.iter_mut().find(|style| style.id == custom.id) {
+                Some(builtin) => {
+                    builtin.name = custom.name.clone();
+                    builtin.prompt = custom.prompt.clone();
+                    builtin.overridden = true;
+                    builtin.variables = variables;
+                    builtin.examples = usable_examples(custom);
+                    builtin.steps = custom.steps.clone();
+                    builtin.template = template;
+                }
+                None => styles.push(Style {
+                    id: custom.id.clone(),
+                    name: custom.name.clone(),
+                    prompt: custom.prompt.clone(),
+                    builtin: false,
+                    overridden: false,
+                    variables,
+                    examples: usable_examples(custom),
+                    steps: custom.steps.clone(),
+                    template,
+                }),
+            }
+        }
+        *self.styles.write().unwrap() = styles;
+    }
+
+    pub fn get(&self, id: &str) -> Option<Style> {
+        self.styles.read().unwrap().iter().find(|style| style.id == id).cloned()
+    }
+
+    pub fn list(&self) -> Vec<Style> {
+        self.styles.read().unwrap().clone()
+    }
+}
+
+impl Default for StyleRegistry {
+    fn default() -> Self {
+        Self::with_builtin_styles()
+    }
+}
+
+/// Checks a custom style before it is saved: its variable names have to be
+/// usable, and its prompt has to parse and use only built-in or declared variables.
+pub fn validate(custom: &CustomStyle) -> Result<Template, StyleError> {
+    let fail = |error| StyleError::Template { style: custom.name.clone(), error };
+
+    for (index, variable) in custom.variables.iter().enumerate() {
+        let name = variable.name.as_str();
+        if !template::is_valid_name(name) {
+            return Err(fail(TemplateError::InvalidName(name.to_string())));
+        }
+        if BUILTIN_VARIABLES.contains(&name) {
+            return Err(fail(TemplateError::ReservedVariable(name.to_string())));
+        }
+        if custom.variables[..index].iter().any(|earlier| earlier.name == name) {
+            return Err(fail(TemplateError::DuplicateVariable(name.to_string())));
+        }
+    }
+
+    let template = Template::parse(&custom.prompt).map_err(fail)?;
+    template
+        .check(|name| BUILTIN_VARIABLES.contains(&name) || custom.variables.iter().any(|v| v.name == name))
+        .map_err(fail)?;
+    Ok(template)
+}
+
+/// Validates every custom style in `config`, including that pipeline steps
+/// name existing styles that are not pipelines themselves.
+pub fn validate_config(config: &AppConfig) -> Result<(), StyleError> {
+    for custom in &config.custom_styles {
+        validate(custom)?;
+
+        for (index, step) in custom.steps.iter().enumerate() {
+            let step_number = index + 1;
+            match config.custom_styles.iter().find(|other| other.id == step.style) {
+                Some(other) if !other.steps.is_empty() => {
+                    return Err(StyleError::NestedPipeline {
+                        style: custom.name.clone(),
+                        step: step_number,
+                        step_style: other.name.clone(),
+                    });
+                }
+                Some(_) => {}
+                None if BUILTIN_STYLES.iter().any(|(id, ..)| *id == step.style) => {}
+                None => {
+                    return Err(StyleError::UnknownStep {
+                        style: custom.name.clone(),
+                        step: step_number,
+                        step_style: step.style.clone(),
+                    });
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Today's date as YYYY-MM-DD in UTC, for when the popup did not send its local date.
+pub fn today() -> String {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+    // Civil-from-days, counting eras of 400 years from 0000-03-01
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn builtin_styles() -> Vec<Style> {
+    BUILTIN_STYLES
+        .iter()
+        .map(|(id, name, prompt)| Style {
+            id: id.to_string(),
+            name: name.to_string(),
+            prompt: prompt.to_string(),
+            builtin: true,
+            overridden: false,
+            variables: Vec::new(),
+            examples: Vec::new(),
+            steps: Vec::new(),
+            template: Template::literal(prompt),
+        })
+        .collect()
+}
+
+// Chat APIs reject empty turns, so half-filled examples are left out
+fn usable_examples(custom: &CustomStyle) -> Vec<StyleExample> {
+    custom
+        .examples
+        .iter()
+        .filter(|example| !example.input.trim().is_empty() && !example.output.trim().is_empty())
+        .cloned()
+        .collect()
+}
+
+fn popup_variables(template: &Template, declared: &[StyleVariable]) -> Vec<StyleVariable> {
+    INPUT_VARIABLES
+        .iter()
+        .filter(|name| template.uses(name))
+        .map(|name| StyleVariable { name: name.to_string(), default: String::new() })
+        .chain(declared.iter().cloned())
+        .collect()
+}
@@ -0,0 +1,319 @@
+// Prompt templates: {{variable}} placeholders and {{#if variable}}…{{else}}…{{/if}} sections
+use std::collections::HashMap;
+
+/// Variables every template can use. The app fills in `text`, `app_name` and
+/// `date`; `language` and `max_length` are asked for in the popup.
+pub const BUILTIN_VARIABLES: [&str; 5] = ["text", "language", "app_name", "max_length", "date"];
+
+/// Why a template or a style's variable list was rejected.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum TemplateError {
+    #[error("`{{{{` at character {0} is never closed with `}}}}`")]
+    UnclosedTag(usize),
+    #[error("`{{{{{0}}}}}` is not a variable or a section tag")]
+    InvalidTag(String),
+    #[error("`{{{{#if {0}}}}}` is never closed with `{{{{/if}}}}`")]
+    UnclosedSection(String),
+    #[error("`{{{{{0}}}}}` has no matching `{{{{#if}}}}`")]
+    UnexpectedTag(String),
+    #[error("Unknown variable `{0}`. Use a built-in variable or declare it on the style.")]
+    UnknownVariable(String),
+    #[error("Invalid variable name `{0}`. Use letters, digits and underscores.")]
+    InvalidName(String),
+    #[error("Variable `{0}` is declared twice")]
+    DuplicateVariable(String),
+    #[error("`{0}` is a built-in variable and cannot be redeclared")]
+    ReservedVariable(String),
+}
+
+/// A parsed prompt template.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Variable(String),
+    /// Renders `then` when the variable is set to something other than
+    /// whitespace, `otherwise` when it is not.
+    Section {
+        variable: String,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+}
+
+// A section still being parsed; the bottom of the stack is the template itself
+struct Open {
+    variable: Option<String>,
+    then: Vec<Node>,
+    otherwise: Option<Vec<Node>>,
+}
+
+impl Open {
+    fn nodes(&mut self) -> &mut Vec<Node> {
+        self.otherwise.as_mut().unwrap_or(&mut self.then)
+    }
+}
+
+enum Tag<'a> {
+    Variable(&'a str),
+    If(&'a str),
+    Else,
+    EndIf,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self, TemplateError> {
+        let mut stack = vec![Open { variable: None, then: Vec::new(), otherwise: None }];
+        let mut rest = source;
+
+        while let Some(start) = rest.find("{{") {
+            push_text(stack.last_mut().unwrap().nodes(), &rest[..start]);
+            let after = &rest[start + 2..];
+            let Some(end) = after.find("}}") else {
+                let offset = source.len() - rest.len() + start;
+                return Err(TemplateError::UnclosedTag(source[..offset].chars().count()));
+            };
+            let raw = after[..end].trim();
+            rest = &after[end + 2..];
+
+            match parse_tag(raw)? {
+                Tag::Variable(name) => stack.last_mut().unwrap().nodes().push(Node::Variable(name.to_string())),
+                Tag::If(name) => stack.push(Open {
+                    variable: Some(name.to_string()),
+                    then: Vec::new(),
+                    otherwise: None,
+                }),
+                Tag::Else => {
+                    let open = stack.last_mut().unwrap();
+                    if open.variable.is_none() || open.otherwise.is_some() {
+                        return Err(TemplateError::UnexpectedTag(raw.to_string()));
+                    }
+                    open.otherwise = Some(Vec::new());
+                }
+                Tag::EndIf => {
+                    if stack.len() == 1 {
+                        return Err(TemplateError::UnexpectedTag(raw.to_string()));
+                    }
+                    let open = stack.pop().unwrap();
+                    let section = Node::Section {
+                        variable: open.variable.unwrap_or_default(),
+                        then: open.then,
+                        otherwise: open.otherwise.unwrap_or_default(),
+                    };
+                    stack.last_mut().unwrap().nodes().push(section);
+                }
+            }
+        }
+        push_text(stack.last_mut().unwrap().nodes(), rest);
+
+        let open = stack.pop().unwrap();
+        match open.variable {
+            Some(variable) => Err(TemplateError::UnclosedSection(variable)),
+            None => Ok(Self { nodes: open.then }),
+        }
+    }
+
+    /// A template that renders `text` as is, braces included.
+    pub fn literal(text: &str) -> Self {
+        Self {
+            nodes: vec![Node::Text(text.to_string())],
+        }
+    }
+
+    /// Every variable the template refers to, in order of first use.
+    pub fn variables(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        collect_variables(&self.nodes, &mut names);
+        names
+    }
+
+    pub fn uses(&self, name: &str) -> bool {
+        self.variables().contains(&name)
+    }
+
+    /// Fails on the first variable `known` does not accept.
+    pub fn check(&self, known: impl Fn(&str) -> bool) -> Result<(), TemplateError> {
+        match self.variables().into_iter().find(|name| !known(name)) {
+            Some(name) => Err(TemplateError::UnknownVariable(name.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// Fills in the template. Missing variables render as nothing.
+    pub fn render(&self, values: &HashMap<String, String>) -> String {
+        let mut out = String::new();
+        render_nodes(&self.nodes, values, &mut out);
+        out
+    }
+}
+
+/// Whether `name` can be used as a variable.
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_tag(raw: &str) -> Result<Tag<'_>, TemplateError> {
+    let tag = match raw {
+        "else" => Tag::Else,
+        "/if" => Tag::EndIf,
+        _ => match raw.strip_prefix("#if") {
+            Some(name) if name.starts_with(char::is_whitespace) => Tag::If(name.trim()),
+            _ => Tag::Variable(raw),
+        },
+    };
+    match tag {
+        Tag::Variable(name) | Tag::If(name) if !is_valid_name(name) => Err(TemplateError::InvalidTag(raw.to_string())),
+        tag => Ok(tag),
+    }
+}
+
+fn push_text(nodes: &mut Vec<Node>, text: &str) {
+    if !text.is_empty() {
+        nodes.push(Node::Text(text.to_string()));
+    }
+}
+
+fn collect_variables<'a>(nodes: &'a [Node], names: &mut Vec<&'a str>) {
+    for node in nodes {
+        match node {
+            Node::Text(_) => {}
+            Node::Variable(name) => {
+                if !names.contains(&name.as_str()) {
+                    names.push(name);
+                }
+            }
+            Node::Section { variable, then, otherwise } => {
+                if !names.contains(&variable.as_str()) {
+                    names.push(variable);
+                }
+                collect_variables(then, names);
+                collect_variables(otherwise, names);
+            }
+        }
+    }
+}
+
+fn render_nodes(nodes: &[Node], values: &HashMap<String, String>, out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Variable(name) => out.push_str(values.get(name).map(String::as_str).unwrap_or_default()),
+            Node::Section { variable, then, otherwise } => {
+                let set = values.get(variable).is_some_and(|value| !value.trim().is_empty());
+                render_nodes(if set { then } else { otherwise }, values, out);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn renders_variables() {
+        let template = Template::parse("Write to {{ recipient }} in {{language}}: {{text}}").unwrap();
+        assert_eq!(template.variables(), vec!["recipient", "language", "text"]);
+        assert!(template.uses("text"));
+        assert_eq!(
+            template.render(&values(&[("recipient", "Sam"), ("language", "French"), ("text", "hi")])),
+            "Write to Sam in French: hi"
+        );
+    }
+
+    #[test]
+    fn missing_variables_render_as_nothing() {
+        let template = Template::parse("Hello {{name}}!").unwrap();
+        assert_eq!(template.render(&HashMap::new()), "Hello !");
+    }
+
+    #[test]
+    fn sections_pick_a_branch() {
+        let template = Template::parse("{{#if recipient}}Address {{recipient}}.{{else}}Stay neutral.{{/if}} Done").unwrap();
+        assert_eq!(template.render(&values(&[("recipient", "Ana")])), "Address Ana. Done");
+        assert_eq!(template.render(&values(&[("recipient", "  ")])), "Stay neutral. Done");
+        assert_eq!(template.render(&HashMap::new()), "Stay neutral. Done");
+    }
+
+    #[test]
+    fn sections_nest() {
+        let template =
+            Template::parse("{{#if a}}A{{#if b}}B{{else}}b{{/if}}{{else}}{{#if b}}only B{{/if}}{{/if}}").unwrap();
+        assert_eq!(template.variables(), vec!["a", "b"]);
+        assert_eq!(template.render(&values(&[("a", "1"), ("b", "1")])), "AB");
+        assert_eq!(template.render(&values(&[("a", "1")])), "Ab");
+        assert_eq!(template.render(&values(&[("b", "1")])), "only B");
+        assert_eq!(template.render(&HashMap::new()), "");
+    }
+
+    #[test]
+    fn literal_keeps_braces() {
+        let template = Template::literal("Keep {{this}} as is");
+        assert!(template.variables().is_empty());
+        assert_eq!(template.render(&values(&[("this", "x")])), "Keep {{this}} as is");
+    }
+
+    #[test]
+    fn check_rejects_unknown_variables() {
+        let template = Template::parse("{{text}} for {{audience}}").unwrap();
+        assert_eq!(
+            template.check(|name| BUILTIN_VARIABLES.contains(&name)),
+            Err(TemplateError::UnknownVariable("audience".to_string()))
+        );
+        assert_eq!(template.check(|name| name == "text" || name == "audience"), Ok(()));
+    }
+
+    #[test]
+    fn unclosed_section() {
+        assert_eq!(
+            Template::parse("{{#if tone}}Be {{tone}}."),
+            Err(TemplateError::UnclosedSection("tone".to_string()))
+        );
+        assert_eq!(
+            Template::parse("{{#if a}}{{#if b}}x{{/if}}"),
+            Err(TemplateError::UnclosedSection("a".to_string()))
+        );
+    }
+
+    #[test]
+    fn stray_section_tags() {
+        assert_eq!(Template::parse("Done{{/if}}"), Err(TemplateError::UnexpectedTag("/if".to_string())));
+        assert_eq!(Template::parse("{{else}}"), Err(TemplateError::UnexpectedTag("else".to_string())));
+        assert_eq!(
+            Template::parse("{{#if a}}x{{else}}y{{else}}z{{/if}}"),
+            Err(TemplateError::UnexpectedTag("else".to_string()))
+        );
+    }
+
+    #[test]
+    fn unclosed_tag_reports_its_position() {
+        assert_eq!(Template::parse("Résumé {{text"), Err(TemplateError::UnclosedTag(7)));
+    }
+
+    #[test]
+    fn invalid_tags() {
+        assert_eq!(Template::parse("{{}}"), Err(TemplateError::InvalidTag(String::new())));
+        assert_eq!(Template::parse("{{two words}}"), Err(TemplateError::InvalidTag("two words".to_string())));
+        assert_eq!(Template::parse("{{#if}}x{{/if}}"), Err(TemplateError::InvalidTag("#if".to_string())));
+        assert_eq!(Template::parse("{{#if 1st}}x{{/if}}"), Err(TemplateError::InvalidTag("#if 1st".to_string())));
+    }
+
+    #[test]
+    fn variable_names() {
+        assert!(is_valid_name("max_length"));
+        assert!(is_valid_name("_private"));
+        assert!(!is_valid_name("2nd"));
+        assert!(!is_valid_name("kebab-case"));
+        assert!(!is_valid_name(""));
+    }
+}
@@ -1,36 +1,74 @@
 import { useState, useEffect, useRef } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import { register, unregister } from '@tauri-apps/plugin-global-shortcut';
-import { RefreshCw, Copy, Settings as SettingsIcon } from 'lucide-react';
+import { RefreshCw, Copy, Replace, Settings as SettingsIcon } from 'lucide-react';
 import Settings from './components/Settings';
-import type { CustomStyle } from './store/appStore';
+import type { Style } from './store/appStore';
 
-const BUILTIN_STYLES = ['professional', 'casual', 'sarcasm'] as const;
+// Structured error returned by `rephrase_text` (see ai::RephraseError)
+interface RephraseError {
+  kind: string;
+  message: string;
+  provider?: string;
+  retry_after?: number | null;
+  vendor_type?: string | null;
+  vendor_message?: string;
+}
+
+// Successful `rephrase_text` result (see ai::RephraseOutcome)
+interface RephraseOutcome {
+  text: string;
+  provider: string;
+  provider_name: string;
+  steps?: StepOutput[];
+}
+
+// One finished step of a pipeline style (see ai::StepOutput)
+interface StepOutput {
+  index: number;
+  style: string;
+  provider: string;
+  provider_name: string;
+  text: string;
+}
+
+const isRephraseError = (e: unknown): e is RephraseError =>
+  typeof e === 'object' && e !== null && 'kind' in e && 'message' in e;
+
+// Errors the user can only fix by changing provider settings
+const SETTINGS_ERROR_KINDS = ['auth', 'missing_api_key', 'model_not_found'];
 
 function App() {
   const [inputText, setInputText] = useState('');
+  // The input is Markdown converted from a formatted selection
+  const [isRich, setIsRich] = useState(false);
   const [rephrasedText, setRephrasedText] = useState('');
+  // Set when a fallback provider, not the selected one, produced the result
+  const [fallbackProvider, setFallbackProvider] = useState<string | null>(null);
+  // Intermediate outputs of a pipeline style, in step order
+  const [pipelineSteps, setPipelineSteps] = useState<StepOutput[]>([]);
+  const [showSteps, setShowSteps] = useState(false);
   const [currentStyle, setCurrentStyle] = useState<string>('professional');
   const [isLoading, setIsLoading] = useState(false);
   const [error, setError] = useState<string | null>(null);
   const [showSettings, setShowSettings] = useState(false);
-  const [customStyles, setCustomStyles] = useState<CustomStyle[]>([]);
-  
-  const getCustomPromptForStyle = (styleId: string): string => {
-    const custom = customStyles.find(s => s.id === styleId);
-    return custom?.prompt || '';
-  };
+  const [styles, setStyles] = useState<Style[]>([]);
 
-  const getStyleLabel = (styleId: string): string => {
-    if (BUILTIN_STYLES.includes(styleId as any)) {
-      return styleId.charAt(0).toUpperCase() + styleId.slice(1);
-    }
-    const custom = customStyles.find(s => s.id === styleId);
-    return custom?.name || styleId;
-  };
+  // Values typed for template variables, shared by every style that uses the name.
+  // Mirrored in a ref so the hotkey handler sees the latest values.
+  const [variableValues, setVariableValues] = useState<Record<string, string>>({});
+  const variableValuesRef = useRef(variableValues);
+  variableValuesRef.current = variableValues;
+
+  // What the popup supplies for a style's template, including the local date
+  const templateVariables = (): Record<string, string> => ({
+    ...variableValuesRef.current,
+    date: new Date().toLocaleDateString('en-CA'),
+  });
 
-  const isBuiltinStyle = (styleId: string): boolean => {
-    return BUILTIN_STYLES.includes(styleId as any);
+  const getStyleLabel = (styleId: string): string => {
+    return styles.find(s => s.id === styleId)?.name || styleId;
   };
 
   const handleSettingsClose = async () => {
@@ -40,7 +78,7 @@ function App() {
       if (config.default_style) {
         setCurrentStyle(config.default_style);
       }
-      setCustomStyles(config.custom_styles || []);
+      setStyles(await invoke<Style[]>('list_styles'));
     } catch (error) {
       console.error('Failed to reload config:', error);
     }
@@ -49,6 +87,41 @@ function App() {
   
   const rephrasedSectionRef = useRef<HTMLDivElement>(null);
 
+  // Streams partial output into the popup while the request is in flight.
+  // The resolved value is the final, preamble-stripped text.
+  const streamRephrase = async (args: { provider: string } & Record<string, unknown>): Promise<string> => {
+    const requestId = crypto.randomUUID();
+    setFallbackProvider(null);
+    setPipelineSteps([]);
+    let streamed = '';
+    const unlisten = await listen<{ request_id: string; delta: string }>('rephrase-chunk', (event) => {
+      if (event.payload.request_id !== requestId) return;
+      streamed += event.payload.delta;
+      setRephrasedText(streamed);
+    });
+    // Each pipeline step streams on its own; start over when one finishes
+    const unlistenSteps = await listen<{ request_id: string } & StepOutput>('rephrase-step', (event) => {
+      if (event.payload.request_id !== requestId) return;
+      const step: StepOutput = event.payload;
+      streamed = '';
+      setPipelineSteps(prev => [...prev, step]);
+      setRephrasedText(step.text);
+    });
+    try {
+      const outcome = await invoke<RephraseOutcome>('rephrase_text', { ...args, requestId });
+      if (outcome.steps?.length) {
+        // Pipeline steps pick their own providers, so none of them is a fallback
+        setPipelineSteps(outcome.steps);
+      } else if (outcome.provider.toLowerCase() !== args.provider.toLowerCase()) {
+        setFallbackProvider(outcome.provider_name);
+      }
+      return outcome.text;
+    } finally {
+      unlisten();
+      unlistenSteps();
+    }
+  };
+
   const handleRephrase = async () => {
     const trimmedText = inputText.trim();
     if (!trimmedText) {
@@ -56,42 +129,32 @@ function App() {
       return;
     }
 
-    if (trimmedText.length > 10000) {
-      setError('Text is too long. Maximum 10,000 characters allowed.');
-      return;
-    }
-
     setIsLoading(true);
     setError(null);
 
     try {
-      const [freshConfig, currentApiKey] = await Promise.all([
-        invoke<any>('load_config'),
-        invoke<string | null>('get_api_key'),
-      ]);
+      const freshConfig = await invoke<any>('load_config');
       const currentProvider = freshConfig.model_provider || 'proxy';
-      
-      if (currentProvider !== 'proxy' && !currentApiKey) {
-        setError('Please configure your API key in Settings or use the default Proxy Server');
-        setShowSettings(true);
-        setIsLoading(false);
-        return;
-      }
 
-      const customPrompt = getCustomPromptForStyle(currentStyle);
-      
-      const rephrased = await invoke<string>('rephrase_text', {
+      const rephrased = await streamRephrase({
         text: trimmedText,
-        style: isBuiltinStyle(currentStyle) ? currentStyle : 'professional',
+        style: currentStyle,
         provider: currentProvider,
-        apiKey: currentApiKey || '',
-        customPrompt: customPrompt || null,
+        variables: templateVariables(),
+        rich: isRich,
       });
 
       setRephrasedText(rephrased);
     } catch (e) {
-      const errorMessage = typeof e === 'string' ? e : 'Failed to rephrase text. Please try again.';
+      if (isRephraseError(e) && e.kind === 'cancelled') return;
+      // Don't leave the streamed answer on screen as if it were a rephrasing
+      if (isRephraseError(e) && e.kind === 'off_task') setRephrasedText('');
+      const errorMessage = isRephraseError(e) ? e.message
+        : typeof e === 'string' ? e : 'Failed to rephrase text. Please try again.';
       setError(errorMessage);
+      if (isRephraseError(e) && SETTINGS_ERROR_KINDS.includes(e.kind)) {
+        setShowSettings(true);
+      }
       console.error('Rephrase error:', e);
     } finally {
       setIsLoading(false);
@@ -102,11 +165,13 @@ function App() {
     if (!rephrasedText) return;
     
     try {
-      await invoke('copy_to_clipboard', { text: rephrasedText });
+      await invoke('copy_to_clipboard', { text: rephrasedText, rich: isRich });
       await invoke('hide_popup');
       
       setInputText('');
+      setIsRich(false);
       setRephrasedText('');
+      setFallbackProvider(null);
       setError(null);
       setIsLoading(false);
     } catch (e) {
@@ -115,12 +180,32 @@ function App() {
     }
   };
 
+  const handleReplace = async () => {
+    if (!rephrasedText) return;
+
+    try {
+      await invoke('replace_selection', { text: rephrasedText, rich: isRich });
+
+      setInputText('');
+      setIsRich(false);
+      setRephrasedText('');
+      setFallbackProvider(null);
+      setError(null);
+      setIsLoading(false);
+    } catch (e) {
+      setError(typeof e === 'string' ? e : 'Failed to replace the selection');
+      await invoke('show_popup_at_cursor');
+      console.error('Replace error:', e);
+    }
+  };
+
   const handlePaste = async () => {
     try {
       const text = await invoke<string>('get_clipboard_text');
       const trimmed = text.trim();
       if (trimmed) {
         setInputText(trimmed);
+        setIsRich(false);
         setError(null);
       } else {
         setError('Clipboard is empty');
@@ -133,6 +218,13 @@ function App() {
 
   useEffect(() => {
     const handleKeyDown = async (e: KeyboardEvent) => {
+      // Cmd/Ctrl+Enter swaps the selection in the source app for the result
+      if ((e.metaKey || e.ctrlKey) && e.key === 'Enter' && rephrasedText) {
+        e.preventDefault();
+        await handleReplace();
+        return;
+      }
+
       if ((e.metaKey || e.ctrlKey) && e.key === 'c') {
         const target = e.target as HTMLElement;
         const isInput = target.tagName === 'TEXTAREA' || target.tagName === 'INPUT';
@@ -146,14 +238,14 @@ function App() {
 
     window.addEventListener('keydown', handleKeyDown);
     return () => window.removeEventListener('keydown', handleKeyDown);
-  }, [rephrasedText]);
+  }, [rephrasedText, isRich]);
 
   useEffect(() => {
     const init = async () => {
       try {
         const config = await invoke<any>('load_config');
         setCurrentStyle(config.default_style || 'professional');
-        setCustomStyles(config.custom_styles || []);
+        setStyles(await invoke<Style[]>('list_styles'));
       } catch (error) {
         console.error('Failed to load initial config:', error);
       }
@@ -197,12 +289,9 @@ function App() {
           isProcessing = true;
           
           try {
-            const [freshConfig, freshApiKey] = await Promise.all([
-              invoke<any>('load_config'),
-              invoke<string | null>('get_api_key'),
-            ]);
+            const freshConfig = await invoke<any>('load_config');
             
-            const text = await invoke<string>('get_selected_text');
+            const { text, rich } = await invoke<{ text: string; rich: boolean }>('get_selected_text');
             
             if (!text || text.trim().length === 0) {
               setError('No text selected. Please select some text and try again.');
@@ -214,44 +303,43 @@ function App() {
             await invoke('show_popup_at_cursor');
             
             setInputText(text);
+            setIsRich(rich);
             setError(null);
             
             const currentProvider = freshConfig.model_provider || 'proxy';
-            const currentApiKey = freshApiKey || '';
-            const latestCustomStyles: CustomStyle[] = freshConfig.custom_styles || [];
-            setCustomStyles(latestCustomStyles);
+            setStyles(await invoke<Style[]>('list_styles'));
+            
+            setIsLoading(true);
             
-            if (currentProvider === 'proxy' || currentApiKey) {
-              setIsLoading(true);
+            try {
+              const rephrased = await streamRephrase({
+                text,
+                style: currentStyle,
+                provider: currentProvider,
+                variables: templateVariables(),
+                rich,
+              });
+              setRephrasedText(rephrased);
               
-              try {
-                const customPrompt = latestCustomStyles.find(s => s.id === currentStyle)?.prompt || '';
-
-                const rephrased = await invoke<string>('rephrase_text', {
-                  text,
-                  style: BUILTIN_STYLES.includes(currentStyle as any) ? currentStyle : 'professional',
-                  provider: currentProvider,
-                  apiKey: currentApiKey,
-                  customPrompt: customPrompt || null,
+              setTimeout(() => {
+                rephrasedSectionRef.current?.scrollIntoView({ 
+                  behavior: 'smooth', 
+                  block: 'nearest' 
                 });
-                setRephrasedText(rephrased);
-                
-                setTimeout(() => {
-                  rephrasedSectionRef.current?.scrollIntoView({ 
-                    behavior: 'smooth', 
-                    block: 'nearest' 
-                  });
-                }, 100);
-              } catch (e) {
-                const errorMessage = typeof e === 'string' ? e : 'Failed to rephrase text';
-                setError(errorMessage);
-                console.error('Rephrase error:', e);
-              } finally {
-                setIsLoading(false);
+              }, 100);
+            } catch (e) {
+              if (isRephraseError(e) && e.kind === 'cancelled') return;
+              // Don't leave the streamed answer on screen as if it were a rephrasing
+              if (isRephraseError(e) && e.kind === 'off_task') setRephrasedText('');
+              const errorMessage = isRephraseError(e) ? e.message
+                : typeof e === 'string' ? e : 'Failed to rephrase text';
+              setError(errorMessage);
+              if (isRephraseError(e) && SETTINGS_ERROR_KINDS.includes(e.kind)) {
+                setShowSettings(true);
               }
-            } else {
-              setError('Please configure your API key in Settings');
-              setShowSettings(true);
+              console.error('Rephrase error:', e);
+            } finally {
+              setIsLoading(false);
             }
           } catch (e) {
             const errorMessage = typeof e === 'string' ? e : 'Failed to capture text. Make sure accessibility permissions are enabled in System Preferences → Security & Privacy → Accessibility.';
@@ -287,10 +375,6 @@ function App() {
     };
   }, [currentStyle]);
 
-  const allStyles = [
-    ...BUILTIN_STYLES.map(s => ({ id: s, label: s.charAt(0).toUpperCase() + s.slice(1) })),
-    ...customStyles.map(s => ({ id: s.id, label: s.name })),
-  ];
 
   return (
     <div className="h-screen bg-gradient-to-br from-blue-50 to-indigo-100 dark:from-gray-900 dark:to-gray-800 p-6">
@@ -313,7 +397,7 @@ function App() {
 
         {/* Style Selector */}
         <div className="flex gap-2 mb-4 flex-wrap">
-          {allStyles.map((style) => (
+          {styles.map((style) => (
             <button
               key={style.id}
               onClick={() => setCurrentStyle(style.id)}
@@ -321,13 +405,29 @@ function App() {
                 currentStyle === style.id
                   ? 'bg-blue-600 text-white shadow-lg transform scale-105'
                   : 'bg-white dark:bg-gray-700 text-gray-700 dark:text-gray-300 hover:bg-blue-50 dark:hover:bg-gray-600'
-              } ${allStyles.length <= 3 ? 'flex-1' : ''}`}
+              } ${styles.length <= 3 ? 'flex-1' : ''}`}
             >
-              {style.label}
+              {style.name}
             </button>
           ))}
         </div>
 
+        {/* Template Variables */}
+        {(styles.find(s => s.id === currentStyle)?.variables.length ?? 0) > 0 && (
+          <div className="flex gap-2 mb-4 flex-wrap">
+            {styles.find(s => s.id === currentStyle)!.variables.map(variable => (
+              <input
+                key={variable.name}
+                type="text"
+                value={variableValues[variable.name] ?? ''}
+                onChange={(e) => setVariableValues({ ...variableValues, [variable.name]: e.target.value })}
+                placeholder={variable.default ? `${variable.name} (${variable.default})` : variable.name}
+                className="flex-1 min-w-[8rem] px-3 py-2 text-sm border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-white focus:ring-2 focus:ring-blue-500 focus:border-transparent"
+              />
+            ))}
+          </div>
+        )}
+
         {/* Input Area */}
         <div className="bg-white dark:bg-gray-800 rounded-xl shadow-lg p-4 mb-4">
           <div className="flex items-center justify-between mb-2">
@@ -372,18 +472,56 @@ function App() {
             <div className="flex items-center justify-between mb-2">
               <label className="text-sm font-semibold text-gray-700 dark:text-gray-300">
                 Rephrased ({getStyleLabel(currentStyle)})
+                {fallbackProvider && (
+                  <span className="ml-2 text-xs font-normal text-gray-500 dark:text-gray-400">
+                    via {fallbackProvider}
+                  </span>
+                )}
               </label>
-              <button
-                onClick={handleCopy}
-                className="flex items-center gap-1 text-xs text-blue-600 hover:text-blue-700 dark:text-blue-400 font-medium"
-              >
-                <Copy size={14} />
-                Copy
-              </button>
+              <div className="flex items-center gap-3">
+                <button
+                  onClick={handleReplace}
+                  title="Replace the selected text (⌘/Ctrl+Enter)"
+                  className="flex items-center gap-1 text-xs text-blue-600 hover:text-blue-700 dark:text-blue-400 font-medium"
+                >
+                  <Replace size={14} />
+                  Replace
+                </button>
+                <button
+                  onClick={handleCopy}
+                  className="flex items-center gap-1 text-xs text-blue-600 hover:text-blue-700 dark:text-blue-400 font-medium"
+                >
+                  <Copy size={14} />
+                  Copy
+                </button>
+              </div>
             </div>
             <div className="flex-1 p-3 border border-blue-200 dark:border-blue-800 rounded-lg bg-blue-50 dark:bg-blue-900/20 text-gray-900 dark:text-white overflow-y-auto">
               {rephrasedText}
             </div>
+            {pipelineSteps.length > 0 && (
+              <div className="mt-3">
+                <button
+                  onClick={() => setShowSteps(!showSteps)}
+                  className="text-xs text-blue-600 hover:text-blue-700 dark:text-blue-400 font-medium"
+                >
+                  {showSteps ? 'Hide' : 'Show'} {pipelineSteps.length} pipeline step{pipelineSteps.length === 1 ? '' : 's'}
+                </button>
+                {showSteps && (
+                  <ol className="mt-2 space-y-2 max-h-48 overflow-y-auto">
+                    {pipelineSteps.map(step => (
+                      <li key={step.index} className="p-2 border border-gray-200 dark:border-gray-600 rounded-lg bg-gray-50 dark:bg-gray-900">
+                        <p className="text-xs font-medium text-gray-700 dark:text-gray-300">
+                          {step.index + 1}. {step.style}
+                          <span className="ml-2 font-normal text-gray-500 dark:text-gray-400">via {step.provider_name}</span>
+                        </p>
+                        <p className="text-xs text-gray-600 dark:text-gray-400 mt-1 whitespace-pre-wrap">{step.text}</p>
+                      </li>
+                    ))}
+                  </ol>
+                )}
+              </div>
+            )}
           </div>
         )}
       </div>
@@ -2,7 +2,132 @@ import { useState, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { useStore } from '../store/appStore';
 import { Save, X, Plus, Trash2, Pencil, Check } from 'lucide-react';
-import type { CustomStyle } from '../store/appStore';
+import type { CustomStyle, CustomEndpoint, PipelineStep, ProviderSettings, Style, StyleExample, StyleVariable } from '../store/appStore';
+
+// What the backend reveals about a stored key without handing it over
+interface ApiKeyStatus {
+  provider: string;
+  present: boolean;
+  last_four: string | null;
+}
+
+// Variables are edited as "recipient, length=200": names with optional defaults
+const formatVariables = (variables: StyleVariable[] = []): string =>
+  variables.map(v => v.default ? `${v.name}=${v.default}` : v.name).join(', ');
+
+const parseVariables = (text: string): StyleVariable[] =>
+  text.split(',').map(part => part.trim()).filter(Boolean).map(part => {
+    const [name, ...rest] = part.split('=');
+    return { name: name.trim(), default: rest.join('=').trim() };
+  });
+
+// Input → output pairs for a style, edited as rows
+const ExamplesEditor = ({ examples, onChange }: { examples: StyleExample[]; onChange: (examples: StyleExample[]) => void }) => {
+  const update = (index: number, example: StyleExample) =>
+    onChange(examples.map((e, i) => i === index ? example : e));
+
+  return (
+    <div className="space-y-2">
+      {examples.map((example, index) => (
+        <div key={index} className="flex items-start gap-2">
+          <textarea
+            value={example.input}
+            onChange={(e) => update(index, { ...example, input: e.target.value })}
+            className="flex-1 px-3 py-1.5 text-xs border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-white resize-none focus:ring-2 focus:ring-blue-500 focus:border-transparent"
+            rows={2}
+            placeholder="Example input"
+          />
+          <textarea
+            value={example.output}
+            onChange={(e) => update(index, { ...example, output: e.target.value })}
+            className="flex-1 px-3 py-1.5 text-xs border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-white resize-none focus:ring-2 focus:ring-blue-500 focus:border-transparent"
+            rows={2}
+            placeholder="How this style rephrases it"
+          />
+          <button
+            onClick={() => onChange(examples.filter((_, i) => i !== index))}
+            className="p-1.5 rounded hover:bg-red-100 dark:hover:bg-red-900/30 text-gray-500 dark:text-gray-400 hover:text-red-600 dark:hover:text-red-400 transition-colors"
+          >
+            <Trash2 size={14} />
+          </button>
+        </div>
+      ))}
+      <button
+        onClick={() => onChange([...examples, { input: '', output: '' }])}
+        className="flex items-center gap-1 text-xs text-blue-600 hover:text-blue-700 dark:text-blue-400 font-medium"
+      >
+        <Plus size={12} /> Add example
+      </button>
+    </div>
+  );
+};
+
+interface Choice {
+  id: string;
+  name: string;
+}
+
+// Styles a pipeline runs in order, each with an optional provider and model
+const StepsEditor = ({ steps, styles, providers, onChange }: {
+  steps: PipelineStep[];
+  styles: Choice[];
+  providers: Choice[];
+  onChange: (steps: PipelineStep[]) => void;
+}) => {
+  const update = (index: number, step: PipelineStep) =>
+    onChange(steps.map((s, i) => i === index ? step : s));
+  const inputClass = "px-2 py-1.5 text-xs border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-white focus:ring-2 focus:ring-blue-500 focus:border-transparent";
+
+  return (
+    <div className="space-y-2">
+      {steps.map((step, index) => (
+        <div key={index} className="flex items-center gap-2">
+          <span className="text-xs text-gray-500 dark:text-gray-400 w-4">{index + 1}.</span>
+          <select
+            value={step.style}
+            onChange={(e) => update(index, { ...step, style: e.target.value })}
+            className={`flex-1 ${inputClass}`}
+          >
+            {styles.map(s => (
+              <option key={s.id} value={s.id}>{s.name}</option>
+            ))}
+          </select>
+          <select
+            value={step.provider || ''}
+            onChange={(e) => update(index, { ...step, provider: e.target.value || null })}
+            className={`flex-1 ${inputClass}`}
+          >
+            <option value="">Default provider</option>
+            {providers.map(p => (
+              <option key={p.id} value={p.id}>{p.name}</option>
+            ))}
+          </select>
+          <input
+            type="text"
+            value={step.model || ''}
+            onChange={(e) => update(index, { ...step, model: e.target.value.trim() || null })}
+            className={`flex-1 ${inputClass}`}
+            placeholder="Default model"
+          />
+          <button
+            onClick={() => onChange(steps.filter((_, i) => i !== index))}
+            className="p-1.5 rounded hover:bg-red-100 dark:hover:bg-red-900/30 text-gray-500 dark:text-gray-400 hover:text-red-600 dark:hover:text-red-400 transition-colors"
+          >
+            <Trash2 size={14} />
+          </button>
+        </div>
+      ))}
+      {styles.length > 0 && (
+        <button
+          onClick={() => onChange([...steps, { style: styles[0].id, provider: null, model: null }])}
+          className="flex items-center gap-1 text-xs text-blue-600 hover:text-blue-700 dark:text-blue-400 font-medium"
+        >
+          <Plus size={12} /> Add pipeline step
+        </button>
+      )}
+    </div>
+  );
+};
 
 interface SettingsProps {
   onClose: () => void;
@@ -10,28 +135,55 @@ interface SettingsProps {
 
 const Settings = ({ onClose }: SettingsProps) => {
   const { config, setConfig } = useStore();
+  // New key typed by the user; empty keeps the stored one
   const [apiKey, setApiKey] = useState('');
+  const [showKey, setShowKey] = useState(false);
   const [modelProvider, setModelProvider] = useState(config?.model_provider || 'proxy');
   const [saving, setSaving] = useState(false);
   const [loading, setLoading] = useState(true);
   const [customStyles, setCustomStyles] = useState<CustomStyle[]>([]);
+  // Styles that ship with the app; a custom style with the same id overrides one
+  const [builtinStyles, setBuiltinStyles] = useState<Style[]>([]);
   const [editingStyle, setEditingStyle] = useState<CustomStyle | null>(null);
   const [newStyleName, setNewStyleName] = useState('');
   const [newStylePrompt, setNewStylePrompt] = useState('');
+  const [newStyleVariables, setNewStyleVariables] = useState('');
+  const [newStyleExamples, setNewStyleExamples] = useState<StyleExample[]>([]);
+  const [newStyleSteps, setNewStyleSteps] = useState<PipelineStep[]>([]);
+  const [editingVariables, setEditingVariables] = useState('');
+  // Why the template being added or edited was rejected
+  const [styleError, setStyleError] = useState<string | null>(null);
   const [showAddForm, setShowAddForm] = useState(false);
+  const [providerSettings, setProviderSettings] = useState<Record<string, ProviderSettings>>({});
+  // Encrypted file credential store waiting for its passphrase
+  const [storeLocked, setStoreLocked] = useState(false);
+  // No store file yet: the passphrase is chosen, and typed twice, instead of checked
+  const [storeMissing, setStoreMissing] = useState(false);
+  const [passphrase, setPassphrase] = useState('');
+  const [confirmation, setConfirmation] = useState('');
+  const [keyStatuses, setKeyStatuses] = useState<ApiKeyStatus[]>([]);
+  const customEndpoints: CustomEndpoint[] = config?.custom_endpoints || [];
+  const isCustomEndpoint = customEndpoints.some(e => e.id === modelProvider);
 
   useEffect(() => {
     const initConfig = async () => {
       try {
-        const [loadedConfig, storedKey] = await Promise.all([
+        const status = await invoke<{ encrypted_file: boolean; exists: boolean; unlocked: boolean }>('credential_store_status');
+        const locked = status.encrypted_file && !status.unlocked;
+        const [loadedConfig, stored, styles] = await Promise.all([
           invoke<any>('load_config'),
-          invoke<string | null>('get_api_key'),
+          locked ? Promise.resolve([]) : invoke<ApiKeyStatus[]>('list_api_keys'),
+          invoke<Style[]>('list_styles'),
         ]);
         
+        setStoreLocked(locked);
+        setStoreMissing(!status.exists);
+        setKeyStatuses(stored);
         setConfig(loadedConfig);
-        setApiKey(storedKey || '');
         setModelProvider(loadedConfig.model_provider || 'proxy');
         setCustomStyles(loadedConfig.custom_styles || []);
+        setBuiltinStyles(styles.filter(s => s.builtin));
+        setProviderSettings(loadedConfig.provider_settings || {});
         setLoading(false);
       } catch (error) {
         console.error('Failed to load config:', error);
@@ -48,10 +200,67 @@ const Settings = ({ onClose }: SettingsProps) => {
     }
   }, [config]);
 
+  // Each provider has its own keychain entry; start with a blank input
+  useEffect(() => {
+    setApiKey('');
+    setShowKey(false);
+  }, [modelProvider]);
+
+  const keyStatus = keyStatuses.find(k => k.provider === modelProvider);
+  const hasStoredKey = (provider: string) => keyStatuses.some(k => k.provider === provider && k.present);
+
+  const keyLabel = (provider: string) =>
+    hasStoredKey(provider) ? 'API key saved' : 'Use your API key';
+
+  const refreshKeyStatuses = async () => {
+    setKeyStatuses(await invoke<ApiKeyStatus[]>('list_api_keys'));
+  };
+
+  const handleRevealKey = async () => {
+    try {
+      setApiKey(await invoke<string>('reveal_api_key', { provider: modelProvider }));
+      setShowKey(true);
+    } catch (error) {
+      console.error('Reveal failed:', error);
+    }
+  };
+
+  const handleRemoveKey = async () => {
+    try {
+      await invoke('delete_api_key', { provider: modelProvider });
+      setApiKey('');
+      await refreshKeyStatuses();
+    } catch (error) {
+      alert(`Failed to remove API key: ${error}`);
+    }
+  };
+
+  const handleUnlock = async () => {
+    try {
+      if (storeMissing) {
+        await invoke('create_credential_store', { passphrase, confirmation });
+      } else {
+        await invoke('unlock_credential_store', { passphrase });
+      }
+      setPassphrase('');
+      setConfirmation('');
+      await refreshKeyStatuses();
+      setStoreLocked(false);
+      setStoreMissing(false);
+    } catch (error) {
+      alert(`${error}`);
+    }
+  };
+
   const handleSave = async () => {
     if (!config) return;
 
-    if (modelProvider !== 'proxy' && !apiKey.trim()) {
+    if (storeLocked && modelProvider !== 'proxy') {
+      alert('Unlock the credential store before saving.');
+      return;
+    }
+
+    if (modelProvider !== 'proxy' && !isCustomEndpoint && !apiKey.trim() && !keyStatus?.present) {
       alert('Please enter an API key for the selected provider, or use "Proxy Server (Default)" to use the app without your own API key.');
       return;
     }
@@ -62,13 +271,16 @@ const Settings = ({ onClose }: SettingsProps) => {
         ...config,
         model_provider: modelProvider,
         custom_styles: customStyles,
+        provider_settings: providerSettings,
       };
       delete (newConfig as any).api_key;
       
-      await Promise.all([
-        invoke('save_config', { config: newConfig }),
-        invoke('set_api_key', { key: apiKey.trim() }),
-      ]);
+      // The key goes to the credential store and provider list the saved
+      // config selects, so the config has to be applied first
+      await invoke('save_config', { config: newConfig });
+      if (!storeLocked && modelProvider !== 'proxy' && apiKey.trim()) {
+        await invoke('set_api_key', { provider: modelProvider, key: apiKey.trim() });
+      }
       
       setConfig(newConfig);
       onClose();
@@ -80,18 +292,38 @@ const Settings = ({ onClose }: SettingsProps) => {
     }
   };
 
-  const handleAddStyle = () => {
-    if (!newStyleName.trim() || !newStylePrompt.trim()) return;
+  // Unknown variables and broken {{#if}} sections are rejected here, before saving
+  const checkStyle = async (style: CustomStyle): Promise<boolean> => {
+    try {
+      await invoke('validate_style', { style });
+      setStyleError(null);
+      return true;
+    } catch (error) {
+      setStyleError(String(error));
+      return false;
+    }
+  };
+
+  const handleAddStyle = async () => {
+    // A pipeline runs its steps' prompts, so it needs no prompt of its own
+    if (!newStyleName.trim() || (!newStylePrompt.trim() && newStyleSteps.length === 0)) return;
     
     const style: CustomStyle = {
       id: `custom_${Date.now()}`,
       name: newStyleName.trim(),
       prompt: newStylePrompt.trim(),
+      variables: parseVariables(newStyleVariables),
+      examples: newStyleExamples,
+      steps: newStyleSteps,
     };
+    if (!(await checkStyle(style))) return;
     
     setCustomStyles(prev => [...prev, style]);
     setNewStyleName('');
     setNewStylePrompt('');
+    setNewStyleVariables('');
+    setNewStyleExamples([]);
+    setNewStyleSteps([]);
     setShowAddForm(false);
   };
 
@@ -101,17 +333,82 @@ const Settings = ({ onClose }: SettingsProps) => {
 
   const handleStartEdit = (style: CustomStyle) => {
     setEditingStyle({ ...style });
+    setEditingVariables(formatVariables(style.variables));
+    setStyleError(null);
   };
 
-  const handleSaveEdit = () => {
-    if (!editingStyle || !editingStyle.name.trim() || !editingStyle.prompt.trim()) return;
+  // Editing a built-in stores the edit as a custom style with the built-in's id
+  const handleSaveEdit = async () => {
+    if (!editingStyle || !editingStyle.name.trim()) return;
+    if (!editingStyle.prompt.trim() && !editingStyle.steps?.length) return;
+    
+    const edited = { ...editingStyle, variables: parseVariables(editingVariables) };
+    if (!(await checkStyle(edited))) return;
     
     setCustomStyles(prev =>
-      prev.map(s => s.id === editingStyle.id ? editingStyle : s)
+      prev.some(s => s.id === edited.id)
+        ? prev.map(s => s.id === edited.id ? edited : s)
+        : [...prev, edited]
     );
     setEditingStyle(null);
   };
 
+  const isBuiltinId = (id: string) => builtinStyles.some(s => s.id === id);
+  const userStyles = customStyles.filter(s => !isBuiltinId(s.id));
+  // Built-ins as they will be after saving, with local overrides applied
+  const shownBuiltins = builtinStyles.map(builtin => {
+    const override = customStyles.find(s => s.id === builtin.id);
+    return {
+      id: builtin.id,
+      name: override?.name ?? builtin.name,
+      // A built-in overridden on disk only knows its stock prompt once saved
+      prompt: override?.prompt ?? (builtin.overridden ? '' : builtin.prompt),
+      variables: override?.variables,
+      examples: override?.examples,
+      steps: override?.steps,
+      overridden: override !== undefined,
+    };
+  });
+  // Pipelines cannot nest, so only plain styles can be steps
+  const stepStyles = (excludeId: string): Choice[] =>
+    [...shownBuiltins, ...userStyles].filter(s => s.id !== excludeId && !s.steps?.length);
+  const stepProviders: Choice[] = [
+    { id: 'proxy', name: 'Proxy Server' },
+    { id: 'openai', name: 'OpenAI' },
+    { id: 'claude', name: 'Anthropic' },
+    { id: 'gemini', name: 'Google Gemini' },
+    { id: 'perplexity', name: 'Perplexity' },
+    ...customEndpoints.map(e => ({ id: e.id, name: e.name })),
+  ];
+
+  const currentSettings: ProviderSettings = providerSettings[modelProvider] || {};
+
+  // Empty inputs clear the override so the provider default applies again
+  const updateProviderSetting = (key: keyof ProviderSettings, raw: string) => {
+    const next: ProviderSettings = { ...currentSettings };
+    const trimmed = raw.trim();
+    if (!trimmed) {
+      delete next[key];
+    } else if (key === 'model') {
+      next.model = trimmed;
+    } else {
+      const value = Number(trimmed);
+      if (Number.isNaN(value)) return;
+      next[key] = value;
+    }
+    setProviderSettings(prev => ({ ...prev, [modelProvider]: next }));
+  };
+
+  const getDefaultModel = () => {
+    switch (modelProvider) {
+      case 'openai': return 'gpt-4o-mini';
+      case 'claude': case 'anthropic': return 'claude-sonnet-4-6';
+      case 'gemini': case 'google': return 'gemini-2.5-flash';
+      case 'perplexity': return 'sonar';
+      default: return customEndpoints.find(e => e.id === modelProvider)?.model || '';
+    }
+  };
+
   const getApiKeyPlaceholder = () => {
     switch (modelProvider) {
       case 'proxy': return 'No API key needed (using default)';
@@ -119,7 +416,7 @@ const Settings = ({ onClose }: SettingsProps) => {
       case 'claude': case 'anthropic': return 'sk-ant-...';
       case 'gemini': case 'google': return 'AIza...';
       case 'perplexity': return 'pplx-...';
-      default: return 'Enter your API key';
+      default: return isCustomEndpoint ? 'Optional — leave empty if the server needs no key' : 'Enter your API key';
     }
   };
 
@@ -141,7 +438,7 @@ const Settings = ({ onClose }: SettingsProps) => {
       case 'claude': case 'anthropic': return 'Anthropic';
       case 'gemini': case 'google': return 'Google';
       case 'perplexity': return 'Perplexity';
-      default: return 'Provider';
+      default: return customEndpoints.find(e => e.id === modelProvider)?.name || 'Provider';
     }
   };
 
@@ -178,52 +475,150 @@ const Settings = ({ onClose }: SettingsProps) => {
             </label>
             <select
               value={modelProvider}
-              onChange={(e) => {
-                setModelProvider(e.target.value);
-                if (e.target.value === 'proxy') {
-                  setApiKey('');
-                }
-              }}
+              onChange={(e) => setModelProvider(e.target.value)}
               className="w-full px-4 py-2 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-white focus:ring-2 focus:ring-blue-500 focus:border-transparent"
             >
               <option value="proxy">Proxy Server (Default - No API key needed)</option>
-              <option value="openai">OpenAI (GPT-4o mini) - Use your API key</option>
-              <option value="claude">Anthropic (Claude Sonnet 4.6) - Use your API key</option>
-              <option value="gemini">Google (Gemini 2.5 Flash) - Use your API key</option>
-              <option value="perplexity">Perplexity (Sonar) - Use your API key</option>
+              <option value="openai">OpenAI (GPT-4o mini) - {keyLabel('openai')}</option>
+              <option value="claude">Anthropic (Claude Sonnet 4.6) - {keyLabel('claude')}</option>
+              <option value="gemini">Google (Gemini 2.5 Flash) - {keyLabel('gemini')}</option>
+              <option value="perplexity">Perplexity (Sonar) - {keyLabel('perplexity')}</option>
+              {customEndpoints.map(e => (
+                <option key={e.id} value={e.id}>
+                  {e.name} ({e.model}) - OpenAI-compatible endpoint{hasStoredKey(e.id) ? ', key saved' : ''}
+                </option>
+              ))}
             </select>
             <p className="mt-2 text-xs text-gray-500 dark:text-gray-400">
               Default uses proxy server (free). Advanced users can use their own API keys.
             </p>
           </div>
 
+          {/* Credential store passphrase */}
+          {modelProvider !== 'proxy' && storeLocked && (
+            <div>
+              <label className="block text-sm font-medium text-gray-700 dark:text-gray-300 mb-2">
+                {storeMissing ? 'Choose a Credential Store Passphrase' : 'Credential Store Passphrase'}
+              </label>
+              <div className="flex gap-2">
+                <input
+                  type="password"
+                  value={passphrase}
+                  placeholder={storeMissing ? 'Passphrase' : undefined}
+                  onChange={(e) => setPassphrase(e.target.value)}
+                  onKeyDown={(e) => e.key === 'Enter' && !storeMissing && handleUnlock()}
+                  className="flex-1 px-4 py-2 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-white focus:ring-2 focus:ring-blue-500 focus:border-transparent"
+                />
+                {storeMissing && (
+                  <input
+                    type="password"
+                    value={confirmation}
+                    placeholder="Repeat passphrase"
+                    onChange={(e) => setConfirmation(e.target.value)}
+                    onKeyDown={(e) => e.key === 'Enter' && handleUnlock()}
+                    className="flex-1 px-4 py-2 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-white focus:ring-2 focus:ring-blue-500 focus:border-transparent"
+                  />
+                )}
+                <button
+                  onClick={handleUnlock}
+                  disabled={!passphrase || (storeMissing && passphrase !== confirmation)}
+                  className="px-4 py-2 bg-blue-600 hover:bg-blue-700 disabled:bg-gray-400 text-white text-sm rounded-lg"
+                >
+                  {storeMissing ? 'Create' : 'Unlock'}
+                </button>
+              </div>
+              <p className="mt-2 text-xs text-gray-500 dark:text-gray-400">
+                {storeMissing
+                  ? 'API keys will be kept in a file encrypted with this passphrase. It cannot be recovered, so type it carefully.'
+                  : 'API keys are kept in an encrypted file.'}
+              </p>
+            </div>
+          )}
+
           {/* API Key */}
-          {modelProvider !== 'proxy' && (
+          {modelProvider !== 'proxy' && !storeLocked && (
             <div>
               <label className="block text-sm font-medium text-gray-700 dark:text-gray-300 mb-2">
                 {getProviderName()} API Key
               </label>
               <input
-                type="password"
+                type={showKey ? 'text' : 'password'}
                 value={apiKey}
                 onChange={(e) => setApiKey(e.target.value)}
-                placeholder={getApiKeyPlaceholder()}
+                placeholder={keyStatus?.present
+                  ? `Saved key${keyStatus.last_four ? ` ending in ${keyStatus.last_four}` : ''} (type to replace)`
+                  : getApiKeyPlaceholder()}
+                className="w-full px-4 py-2 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-white focus:ring-2 focus:ring-blue-500 focus:border-transparent"
+              />
+              {keyStatus?.present && (
+                <div className="mt-2 flex gap-3 text-xs">
+                  <button onClick={handleRevealKey} className="text-blue-500 hover:underline">
+                    Reveal
+                  </button>
+                  <button onClick={handleRemoveKey} className="text-red-500 hover:underline">
+                    Remove
+                  </button>
+                </div>
+              )}
+              {isCustomEndpoint ? (
+                <p className="mt-2 text-xs text-gray-500 dark:text-gray-400">
+                  Sent to {customEndpoints.find(e => e.id === modelProvider)?.base_url}
+                </p>
+              ) : (
+                <p className="mt-2 text-xs text-gray-500 dark:text-gray-400">
+                  Get your API key from{' '}
+                  <a
+                    href={getApiKeyLink()}
+                    target="_blank"
+                    rel="noopener noreferrer"
+                    className="text-blue-500 hover:underline"
+                  >
+                    {getProviderName()} Platform
+                  </a>
+                </p>
+              )}
+            </div>
+          )}
+          
+          {/* Model & Generation Parameters */}
+          {modelProvider !== 'proxy' && (
+            <div>
+              <label className="block text-sm font-medium text-gray-700 dark:text-gray-300 mb-2">
+                Model
+              </label>
+              <input
+                type="text"
+                value={currentSettings.model || ''}
+                onChange={(e) => updateProviderSetting('model', e.target.value)}
+                placeholder={getDefaultModel()}
                 className="w-full px-4 py-2 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-white focus:ring-2 focus:ring-blue-500 focus:border-transparent"
               />
+              <div className="grid grid-cols-2 gap-2 mt-2">
+                {([
+                  ['temperature', 'Temperature', '0.1'],
+                  ['top_p', 'Top P', '0.05'],
+                  ['max_output_tokens', 'Max output tokens', '1'],
+                  ['timeout_secs', 'Timeout (seconds)', '1'],
+                ] as const).map(([key, label, step]) => (
+                  <input
+                    key={key}
+                    type="number"
+                    step={step}
+                    min="0"
+                    value={currentSettings[key] ?? ''}
+                    onChange={(e) => updateProviderSetting(key, e.target.value)}
+                    placeholder={label}
+                    title={label}
+                    className="w-full px-3 py-1.5 text-sm border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-white focus:ring-2 focus:ring-blue-500 focus:border-transparent"
+                  />
+                ))}
+              </div>
               <p className="mt-2 text-xs text-gray-500 dark:text-gray-400">
-                Get your API key from{' '}
-                <a
-                  href={getApiKeyLink()}
-                  target="_blank"
-                  rel="noopener noreferrer"
-                  className="text-blue-500 hover:underline"
-                >
-                  {getProviderName()} Platform
-                </a>
+                Leave blank to use the provider defaults. Change the model here if a vendor renames or retires it.
               </p>
             </div>
           )}
-          
+
           {modelProvider === 'proxy' && (
             <div className="p-4 bg-green-50 dark:bg-green-900/20 border border-green-200 dark:border-green-800 rounded-lg">
               <p className="text-sm text-green-800 dark:text-green-400">
@@ -263,15 +658,110 @@ const Settings = ({ onClose }: SettingsProps) => {
               }}
               className="w-full px-4 py-2 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-white focus:ring-2 focus:ring-blue-500 focus:border-transparent"
             >
-              <option value="professional">Professional</option>
-              <option value="casual">Casual</option>
-              <option value="sarcasm">Sarcasm</option>
-              {customStyles.map(s => (
+              {shownBuiltins.map(s => (
+                <option key={s.id} value={s.id}>{s.name}</option>
+              ))}
+              {userStyles.map(s => (
                 <option key={s.id} value={s.id}>{s.name}</option>
               ))}
             </select>
           </div>
 
+          {/* Built-in Styles */}
+          <div>
+            <label className="block text-sm font-medium text-gray-700 dark:text-gray-300 mb-2">
+              Built-in Styles
+            </label>
+            <p className="text-xs text-gray-500 dark:text-gray-400 mb-3">
+              Edit a built-in style to change its name or prompt. Reset brings back the original.
+            </p>
+            <div className="space-y-2">
+              {shownBuiltins.map(style => (
+                <div
+                  key={style.id}
+                  className="border border-gray-200 dark:border-gray-600 rounded-lg p-3 bg-gray-50 dark:bg-gray-900"
+                >
+                  {editingStyle?.id === style.id ? (
+                    <div className="space-y-2">
+                      <input
+                        type="text"
+                        value={editingStyle.name}
+                        onChange={(e) => setEditingStyle({ ...editingStyle, name: e.target.value })}
+                        className="w-full px-3 py-1.5 text-sm border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-white focus:ring-2 focus:ring-blue-500 focus:border-transparent"
+                        placeholder="Style name"
+                      />
+                      <textarea
+                        value={editingStyle.prompt}
+                        onChange={(e) => setEditingStyle({ ...editingStyle, prompt: e.target.value })}
+                        className="w-full px-3 py-1.5 text-sm border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-white resize-none focus:ring-2 focus:ring-blue-500 focus:border-transparent"
+                        rows={3}
+                        placeholder="Describe the tone and style..."
+                      />
+                      <input
+                        type="text"
+                        value={editingVariables}
+                        onChange={(e) => setEditingVariables(e.target.value)}
+                        className="w-full px-3 py-1.5 text-sm border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-white focus:ring-2 focus:ring-blue-500 focus:border-transparent"
+                        placeholder="Variables (e.g., recipient, length=200)"
+                      />
+                      <ExamplesEditor
+                        examples={editingStyle.examples || []}
+                        onChange={(examples) => setEditingStyle({ ...editingStyle, examples })}
+                      />
+                      {styleError && (
+                        <p className="text-xs text-red-600 dark:text-red-400">{styleError}</p>
+                      )}
+                      <div className="flex justify-end gap-2">
+                        <button
+                          onClick={() => setEditingStyle(null)}
+                          className="px-2 py-1 text-xs text-gray-600 dark:text-gray-400 hover:bg-gray-200 dark:hover:bg-gray-700 rounded"
+                        >
+                          Cancel
+                        </button>
+                        <button
+                          onClick={handleSaveEdit}
+                          className="flex items-center gap-1 px-2 py-1 text-xs text-blue-600 hover:bg-blue-50 dark:hover:bg-blue-900/30 rounded"
+                        >
+                          <Check size={12} /> Save
+                        </button>
+                      </div>
+                    </div>
+                  ) : (
+                    <div className="flex items-start justify-between gap-2">
+                      <div className="flex-1 min-w-0">
+                        <p className="text-sm font-medium text-gray-900 dark:text-white">
+                          {style.name}
+                          {style.overridden && (
+                            <span className="ml-2 text-xs font-normal text-blue-600 dark:text-blue-400">edited</span>
+                          )}
+                        </p>
+                        <p className="text-xs text-gray-500 dark:text-gray-400 mt-0.5 line-clamp-2">
+                          {style.prompt || 'Original prompt'}
+                        </p>
+                      </div>
+                      <div className="flex items-center gap-1 shrink-0">
+                        <button
+                          onClick={() => handleStartEdit({ id: style.id, name: style.name, prompt: style.prompt, variables: style.variables, examples: style.examples })}
+                          className="p-1.5 rounded hover:bg-gray-200 dark:hover:bg-gray-700 text-gray-500 dark:text-gray-400 transition-colors"
+                        >
+                          <Pencil size={14} />
+                        </button>
+                        {style.overridden && (
+                          <button
+                            onClick={() => handleDeleteStyle(style.id)}
+                            className="px-2 py-1 text-xs rounded hover:bg-gray-200 dark:hover:bg-gray-700 text-gray-500 dark:text-gray-400 transition-colors"
+                          >
+                            Reset
+                          </button>
+                        )}
+                      </div>
+                    </div>
+                  )}
+                </div>
+              ))}
+            </div>
+          </div>
+
           {/* Custom Styles */}
           <div>
             <div className="flex items-center justify-between mb-3">
@@ -291,12 +781,14 @@ const Settings = ({ onClose }: SettingsProps) => {
 
             <p className="text-xs text-gray-500 dark:text-gray-400 mb-3">
               Create your own rephrasing tones. Describe how you want the AI to rephrase your text.
+              Prompts can use {'{{text}}'}, {'{{language}}'}, {'{{app_name}}'}, {'{{max_length}}'}, {'{{date}}'} and
+              your own variables, with {'{{#if name}}…{{else}}…{{/if}}'} for optional parts.
             </p>
 
             {/* Existing Custom Styles */}
-            {customStyles.length > 0 && (
+            {userStyles.length > 0 && (
               <div className="space-y-2 mb-3">
-                {customStyles.map(style => (
+                {userStyles.map(style => (
                   <div
                     key={style.id}
                     className="border border-gray-200 dark:border-gray-600 rounded-lg p-3 bg-gray-50 dark:bg-gray-900"
@@ -317,6 +809,26 @@ const Settings = ({ onClose }: SettingsProps) => {
                           rows={3}
                           placeholder="Describe the tone and style..."
                         />
+                        <input
+                          type="text"
+                          value={editingVariables}
+                          onChange={(e) => setEditingVariables(e.target.value)}
+                          className="w-full px-3 py-1.5 text-sm border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-white focus:ring-2 focus:ring-blue-500 focus:border-transparent"
+                          placeholder="Variables (e.g., recipient, length=200)"
+                        />
+                        <ExamplesEditor
+                          examples={editingStyle.examples || []}
+                          onChange={(examples) => setEditingStyle({ ...editingStyle, examples })}
+                        />
+                        <StepsEditor
+                          steps={editingStyle.steps || []}
+                          styles={stepStyles(editingStyle.id)}
+                          providers={stepProviders}
+                          onChange={(steps) => setEditingStyle({ ...editingStyle, steps })}
+                        />
+                        {styleError && (
+                          <p className="text-xs text-red-600 dark:text-red-400">{styleError}</p>
+                        )}
                         <div className="flex justify-end gap-2">
                           <button
                             onClick={() => setEditingStyle(null)}
@@ -376,12 +888,36 @@ const Settings = ({ onClose }: SettingsProps) => {
                   rows={3}
                   placeholder="Describe how the AI should rephrase text (e.g., 'Rephrase in a warm, empathetic tone as if writing to a close friend who needs encouragement')"
                 />
+                <input
+                  type="text"
+                  value={newStyleVariables}
+                  onChange={(e) => setNewStyleVariables(e.target.value)}
+                  className="w-full px-3 py-1.5 text-sm border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-white focus:ring-2 focus:ring-blue-500 focus:border-transparent"
+                  placeholder="Variables (e.g., recipient, length=200)"
+                />
+                <ExamplesEditor
+                  examples={newStyleExamples}
+                  onChange={setNewStyleExamples}
+                />
+                <StepsEditor
+                  steps={newStyleSteps}
+                  styles={stepStyles('')}
+                  providers={stepProviders}
+                  onChange={setNewStyleSteps}
+                />
+                {styleError && (
+                  <p className="text-xs text-red-600 dark:text-red-400">{styleError}</p>
+                )}
                 <div className="flex justify-end gap-2">
                   <button
                     onClick={() => {
                       setShowAddForm(false);
                       setNewStyleName('');
                       setNewStylePrompt('');
+                      setNewStyleVariables('');
+                      setNewStyleExamples([]);
+                      setNewStyleSteps([]);
+                      setStyleError(null);
                     }}
                     className="px-3 py-1.5 text-xs text-gray-600 dark:text-gray-400 hover:bg-gray-200 dark:hover:bg-gray-700 rounded-lg transition-colors"
                   >
@@ -389,7 +925,7 @@ const Settings = ({ onClose }: SettingsProps) => {
                   </button>
                   <button
                     onClick={handleAddStyle}
-                    disabled={!newStyleName.trim() || !newStylePrompt.trim()}
+                    disabled={!newStyleName.trim() || (!newStylePrompt.trim() && newStyleSteps.length === 0)}
                     className="flex items-center gap-1 px-3 py-1.5 text-xs bg-blue-500 text-white rounded-lg hover:bg-blue-600 transition-colors disabled:opacity-50 disabled:cursor-not-allowed"
                   >
                     <Plus size={12} /> Add Style
@@ -398,7 +934,7 @@ const Settings = ({ onClose }: SettingsProps) => {
               </div>
             )}
 
-            {customStyles.length === 0 && !showAddForm && (
+            {userStyles.length === 0 && !showAddForm && (
               <div className="border border-dashed border-gray-300 dark:border-gray-600 rounded-lg p-4 text-center">
                 <p className="text-xs text-gray-500 dark:text-gray-400">
                   No custom styles yet. Click "Add Style" to create your own rephrase tone.
@@ -4,7 +4,62 @@ import { invoke } from '@tauri-apps/api/core';
 export interface CustomStyle {
   id: string;
   name: string;
+  // Template: {{variable}} placeholders and {{#if variable}}…{{else}}…{{/if}} sections
   prompt: string;
+  variables?: StyleVariable[];
+  // Sample rewrites sent to the model as earlier conversation turns
+  examples?: StyleExample[];
+  // Makes the style a pipeline: these styles run in order and the prompt is unused
+  steps?: PipelineStep[];
+}
+
+// One stage of a pipeline style; blank provider and model use the Settings defaults
+export interface PipelineStep {
+  style: string;
+  provider: string | null;
+  model: string | null;
+}
+
+export interface StyleExample {
+  input: string;
+  output: string;
+}
+
+// A variable a custom style declares; the popup asks for its value
+export interface StyleVariable {
+  name: string;
+  default: string;
+}
+
+// A style from `list_styles` (see styles::Style). A custom style whose id
+// matches a built-in overrides it.
+export interface Style {
+  id: string;
+  name: string;
+  prompt: string;
+  builtin: boolean;
+  overridden: boolean;
+  variables: StyleVariable[];
+  steps: PipelineStep[];
+}
+
+export interface CustomEndpoint {
+  id: string;
+  name: string;
+  base_url: string;
+  model: string;
+  auth_header?: string | null;
+  extra_headers?: Record<string, string>;
+  max_input_chars?: number | null;
+  models?: string[];
+}
+
+export interface ProviderSettings {
+  model?: string;
+  temperature?: number;
+  max_output_tokens?: number;
+  top_p?: number;
+  timeout_secs?: number;
 }
 
 interface AppConfig {
@@ -15,6 +70,8 @@ interface AppConfig {
   start_on_login: boolean;
   auto_update: boolean;
   custom_styles: CustomStyle[];
+  custom_endpoints: CustomEndpoint[];
+  provider_settings: Record<string, ProviderSettings>;
 }
 
 interface AppState {